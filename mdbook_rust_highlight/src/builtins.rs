@@ -0,0 +1,191 @@
+//! Names from the standard library, known before any chapter is highlighted.
+
+pub const STD_TRAITS: &[&str] = &[
+    "Any",
+    "AsMut",
+    "AsRef",
+    "Borrow",
+    "BorrowMut",
+    "Clone",
+    "Copy",
+    "Debug",
+    "Default",
+    "Deref",
+    "DerefMut",
+    "Display",
+    "DoubleEndedIterator",
+    "Drop",
+    "Eq",
+    "Error",
+    "ExactSizeIterator",
+    "Extend",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "FromIterator",
+    "FromStr",
+    "Future",
+    "Hash",
+    "Hasher",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Read",
+    "Send",
+    "Sized",
+    "Sync",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+    "Unpin",
+    "Write",
+];
+
+pub const STD_STRUCTS: &[&str] = &[
+    "Arc",
+    "BTreeMap",
+    "BTreeSet",
+    "BinaryHeap",
+    "Box",
+    "Cell",
+    "Duration",
+    "HashMap",
+    "HashSet",
+    "Instant",
+    "LinkedList",
+    "Mutex",
+    "NonNull",
+    "OnceCell",
+    "Path",
+    "PathBuf",
+    "PhantomData",
+    "Pin",
+    "Rc",
+    "RefCell",
+    "RwLock",
+    "String",
+    "Vec",
+    "VecDeque",
+    "Weak",
+];
+
+pub const STD_ENUMS: &[&str] = &["Cow", "Option", "Ordering", "Result"];
+
+pub const STD_VARIANTS: &[&str] = &["Err", "None", "Ok", "Some"];
+
+pub const PRIMITIVE_TYPES: &[&str] = &[
+    "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize", "f16", "f32", "f64", "f128",
+];
+
+/// Builtin macros taking comma separated expressions.
+pub const STD_EXPR_MACROS: &[&str] = &[
+    "concat",
+    "dbg",
+    "env",
+    "include",
+    "include_bytes",
+    "include_str",
+    "option_env",
+];
+
+/// Builtin macros taking a format string, with the index of the argument it is passed as.
+pub const STD_FORMAT_MACROS: &[(&str, usize)] = &[
+    ("assert", 1),
+    ("assert_eq", 2),
+    ("assert_ne", 2),
+    ("debug_assert", 1),
+    ("debug_assert_eq", 2),
+    ("debug_assert_ne", 2),
+    ("eprint", 0),
+    ("eprintln", 0),
+    ("format", 0),
+    ("format_args", 0),
+    ("panic", 0),
+    ("print", 0),
+    ("println", 0),
+    ("todo", 0),
+    ("unimplemented", 0),
+    ("unreachable", 0),
+    ("write", 1),
+    ("writeln", 1),
+];
+
+/// Builtin macros taking either comma separated expressions or `elem; len`.
+pub const STD_VEC_MACROS: &[&str] = &["vec"];
+
+/// Builtin macros taking `expr, pattern if guard`.
+pub const STD_MATCHES_MACROS: &[&str] = &["matches", "assert_matches", "debug_assert_matches"];
+
+/// Inline assembly macros, taking template strings followed by operands.
+pub const STD_ASM_MACROS: &[&str] = &["asm", "global_asm", "naked_asm"];
+
+/// Registers of x86 and ARM that can't be told apart by a numbered prefix.
+pub const ASM_REGISTERS: &[&str] = &[
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "rip", "eax", "ebx", "ecx", "edx",
+    "esi", "edi", "ebp", "esp", "eip", "ax", "bx", "cx", "dx", "si", "di", "bp", "sp", "ip", "al",
+    "bl", "cl", "dl", "ah", "bh", "ch", "dh", "sil", "dil", "bpl", "spl", "cs", "ds", "es", "fs",
+    "gs", "ss", "rflags", "eflags", "lr", "pc", "fp", "xzr", "wzr", "nzcv", "fpcr", "fpsr",
+];
+
+/// Numbered register prefixes of x86 and ARM, with the highest register number.
+pub const ASM_REGISTER_PREFIXES: &[(&str, u32)] = &[
+    ("r", 15),
+    ("xmm", 31),
+    ("ymm", 31),
+    ("zmm", 31),
+    ("mm", 7),
+    ("st", 7),
+    ("k", 7),
+    ("cr", 15),
+    ("dr", 15),
+    ("x", 30),
+    ("w", 30),
+    ("v", 31),
+    ("q", 31),
+    ("d", 31),
+    ("s", 31),
+    ("h", 31),
+    ("b", 31),
+    ("p", 15),
+    ("z", 31),
+];
+
+/// Keywords that change the control flow.
+pub const CONTROL_FLOW_KEYWORDS: &[&str] = &[
+    "await", "become", "break", "continue", "do", "else", "for", "if", "in", "loop", "match",
+    "return", "try", "while", "yield",
+];
+
+/// Keywords that declare or qualify storage.
+pub const STORAGE_KEYWORDS: &[&str] = &["const", "let", "move", "mut", "ref", "static"];
+
+/// Keywords that declare items.
+pub const DECLARATION_KEYWORDS: &[&str] = &[
+    "enum",
+    "extern",
+    "fn",
+    "impl",
+    "macro",
+    "macro_rules",
+    "mod",
+    "struct",
+    "trait",
+    "type",
+    "union",
+    "use",
+];
+
+/// Strict and reserved keywords, used where there is no syntax tree to tell them apart.
+pub const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "super", "trait", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "gen", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield",
+];
@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+use syn::{
+    Expr, Ident, LitStr, Token,
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    token::Paren,
+};
+
+use crate::{
+    builtins::{ASM_REGISTER_PREFIXES, ASM_REGISTERS},
+    highlighter::RustHighlighter,
+    tokens::TokenTag,
+};
+
+/// The register an operand is allocated to.
+pub(crate) enum AsmRegister {
+    /// A register class, as in `in(reg)`.
+    Class(Ident),
+    /// An explicit register, as in `in("eax")`.
+    Explicit(LitStr),
+}
+
+/// A single argument of `asm!`, `naked_asm!` or `global_asm!`.
+pub(crate) enum AsmArg {
+    /// A template string.
+    Template(LitStr),
+    /// `name = inout(reg) expr => expr`, `sym path`, `const expr` or `label { .. }`.
+    Operand {
+        name: Option<(Ident, Token![=])>,
+        keyword: Ident,
+        register: Option<AsmRegister>,
+        expr: Box<Expr>,
+        output: Option<(Token![=>], Box<Expr>)>,
+    },
+    /// `options(nomem, nostack)`
+    Options {
+        keyword: Ident,
+        flags: Punctuated<Ident, Token![,]>,
+    },
+    /// `clobber_abi("C")`
+    ClobberAbi {
+        keyword: Ident,
+        abis: Punctuated<LitStr, Token![,]>,
+    },
+    /// A template built by a macro, such as `concat!(..)`.
+    Expr(Box<Expr>),
+}
+
+impl Parse for AsmRegister {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        match input.peek(LitStr) {
+            true => Ok(Self::Explicit(input.parse()?)),
+            false => Ok(Self::Class(input.parse()?)),
+        }
+    }
+}
+
+impl Parse for AsmArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Self::Template(input.parse()?));
+        }
+        let name = match input.peek(Ident::peek_any) && input.peek2(Token![=]) {
+            true => Some((input.call(Ident::parse_any)?, input.parse()?)),
+            false => None,
+        };
+        let keyword = input.fork().call(Ident::parse_any).ok();
+        let keyword = keyword.as_ref().map(Ident::to_string);
+        match keyword.as_deref() {
+            Some("options" | "clobber_abi") if name.is_none() && input.peek2(Paren) => {
+                let keyword: Ident = input.parse()?;
+                let content;
+                parenthesized!(content in input);
+                match keyword == "options" {
+                    true => Ok(Self::Options {
+                        keyword,
+                        flags: Punctuated::parse_terminated(&content)?,
+                    }),
+                    false => Ok(Self::ClobberAbi {
+                        keyword,
+                        abis: Punctuated::parse_terminated(&content)?,
+                    }),
+                }
+            }
+            Some("in" | "out" | "lateout" | "inout" | "inlateout") if input.peek2(Paren) => {
+                let keyword = input.call(Ident::parse_any)?;
+                let content;
+                parenthesized!(content in input);
+                let register = Some(content.parse()?);
+                let expr = input.parse()?;
+                let output = match input.peek(Token![=>]) {
+                    true => Some((input.parse()?, input.parse()?)),
+                    false => None,
+                };
+                Ok(Self::Operand {
+                    name,
+                    keyword,
+                    register,
+                    expr,
+                    output,
+                })
+            }
+            Some("sym" | "const" | "label") => Ok(Self::Operand {
+                name,
+                keyword: input.call(Ident::parse_any)?,
+                register: None,
+                expr: input.parse()?,
+                output: None,
+            }),
+            _ if name.is_none() => Ok(Self::Expr(input.parse()?)),
+            _ => Err(input.error("expected an asm operand")),
+        }
+    }
+}
+
+/// Whether `word` names an x86 or ARM register, like `rax`, `r8d` or `x29`.
+fn is_asm_register(word: &str) -> bool {
+    let word = word.to_ascii_lowercase();
+    if ASM_REGISTERS.contains(&word.as_str()) {
+        return true;
+    }
+    ASM_REGISTER_PREFIXES.iter().any(|(prefix, max)| {
+        let Some(number) = word.strip_prefix(prefix) else {
+            return false;
+        };
+        // The sub registers of `r8` to `r15`, as in `r8d`.
+        let number = match *prefix {
+            "r" => number.trim_end_matches(['d', 'w', 'b']),
+            _ => number,
+        };
+        !number.starts_with('+') && number.parse::<u32>().is_ok_and(|number| number <= *max)
+    })
+}
+
+fn is_asm_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'.'
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Register the arguments of an inline assembly macro.
+    pub(crate) fn register_asm_args(&mut self, token: &'ast Punctuated<AsmArg, Token![,]>) {
+        let named: HashSet<String> = token
+            .iter()
+            .filter_map(|arg| match arg {
+                AsmArg::Operand {
+                    name: Some((name, _)),
+                    ..
+                } => Some(name.to_string()),
+                _ => None,
+            })
+            .collect();
+
+        for arg in token {
+            match arg {
+                AsmArg::Template(template) => self.register_asm_template(template, &named),
+                AsmArg::Operand {
+                    name,
+                    keyword,
+                    register,
+                    expr,
+                    output,
+                } => {
+                    if let Some((name, eq_token)) = name {
+                        self.register_parameter_tag(name);
+                        self.register_operator_tag(eq_token);
+                    }
+                    self.register_keyword_tag(keyword);
+                    match register {
+                        Some(AsmRegister::Class(class)) => self.register_asmregister_tag(class),
+                        Some(AsmRegister::Explicit(register)) => {
+                            self.register_asmregister_tag(register)
+                        }
+                        None => {}
+                    }
+                    self.register_expr(expr);
+                    if let Some((arrow, output)) = output {
+                        self.register_operator_tag(arrow);
+                        self.register_expr(output);
+                    }
+                }
+                AsmArg::Options { keyword, flags } => {
+                    self.register_keyword_tag(keyword);
+                    for flag in flags {
+                        self.register_asmoption_tag(flag);
+                    }
+                }
+                AsmArg::ClobberAbi { keyword, abis } => {
+                    self.register_keyword_tag(keyword);
+                    for abi in abis {
+                        self.register_lit_str(abi);
+                    }
+                }
+                AsmArg::Expr(expr) => self.register_expr(expr),
+            }
+        }
+    }
+
+    /// Register the placeholders, mnemonics, registers and numbers of an
+    /// assembly template.
+    ///
+    /// The first word of every instruction is its mnemonic, unless it is
+    /// followed by `:`, making it a label.
+    fn register_asm_template(&mut self, token: &LitStr, named: &HashSet<String>) {
+        self.register_lit_str(token);
+        self.register_format_string(token, named);
+
+        let source = token.token().to_string();
+        let offset = token.span().byte_range().start;
+        let raw = source.starts_with('r');
+        let bytes = source.as_bytes();
+        let (Some(open), Some(close)) = (source.find('"'), source.rfind('"')) else {
+            return;
+        };
+
+        let mut instruction_start = true;
+        let mut i = open + 1;
+        while i < close {
+            match (bytes[i], bytes.get(i + 1)) {
+                (b'\\', Some(b'n')) if !raw => {
+                    instruction_start = true;
+                    i += 2;
+                }
+                (b'\\', _) if !raw => i += 2,
+                (b'\n' | b';', _) => {
+                    instruction_start = true;
+                    i += 1;
+                }
+                (b'/', Some(b'/')) => {
+                    while i < close && bytes[i] != b'\n' && (raw || !source[i..].starts_with("\\n"))
+                    {
+                        i += 1;
+                    }
+                }
+                (b'{', Some(b'{')) => i += 2,
+                (b'{', _) => {
+                    instruction_start = false;
+                    i = source[i..close].find('}').map_or(close, |end| i + end + 1);
+                }
+                (byte, _) if is_asm_word(byte) => {
+                    let start = i;
+                    while i < close && is_asm_word(bytes[i]) {
+                        i += 1;
+                    }
+                    let word = &source[start..i];
+                    let tag = if instruction_start && bytes[i] == b':' {
+                        None
+                    } else if instruction_start {
+                        instruction_start = false;
+                        Some(TokenTag::AsmMnemonic)
+                    } else if is_asm_register(word) {
+                        Some(TokenTag::AsmRegister)
+                    } else if byte.is_ascii_digit() {
+                        Some(TokenTag::LitNum)
+                    } else {
+                        None
+                    };
+                    if let Some(tag) = tag {
+                        self.register_tag_at_index(offset + start, offset + i, tag);
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn operand_keywords_are_plain_keywords() {
+        let html = highlight(
+            "fn main() {\n    unsafe { asm!(\"mov {0}, {1}\", in(reg_byte) x, const N) };\n}\n",
+        );
+        assert!(html.contains("<span class=\"hlrs-Keyword\">in</span>"));
+        assert!(html.contains("<span class=\"hlrs-Keyword\">const</span>"));
+    }
+}
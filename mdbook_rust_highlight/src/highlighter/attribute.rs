@@ -0,0 +1,66 @@
+use syn::{AttrStyle, Attribute, Meta, visit::Visit};
+
+use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+
+/// Every attribute of a snippet, wherever it is attached.
+#[derive(Default)]
+pub(crate) struct Attributes<'ast> {
+    pub(crate) attributes: Vec<&'ast Attribute>,
+}
+
+impl<'ast> Visit<'ast> for Attributes<'ast> {
+    fn visit_attribute(&mut self, i: &'ast Attribute) {
+        self.attributes.push(i);
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    pub(crate) fn register_attribute(&mut self, token: &'ast Attribute) {
+        // Doc comments are attributes as well, spanning the whole comment,
+        // and are highlighted as comments. `#[doc = ".."]` is not one.
+        if token.path().is_ident("doc")
+            && token
+                .pound_token
+                .span
+                .source_text()
+                .is_some_and(|text| text.starts_with('/'))
+        {
+            return;
+        }
+        self.register_attribute_tag(&token.pound_token);
+        if let AttrStyle::Inner(bang) = &token.style {
+            self.register_attribute_tag(bang);
+        }
+        match &token.meta {
+            Meta::Path(path) => {
+                self.register_tag(path, TokenTag::Attribute);
+            }
+            Meta::List(list) => {
+                self.register_tag(&list.path, TokenTag::Attribute);
+                self.register_token_stream(list.tokens.clone());
+            }
+            Meta::NameValue(name_value) => {
+                self.register_tag(&name_value.path, TokenTag::Attribute);
+                self.register_operator_tag(&name_value.eq_token);
+                self.register_expr(&name_value.value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn explicit_doc_attributes_are_highlighted() {
+        let html = highlight("/// Sugared.\n#[doc(hidden)]\n#[doc = \"explicit\"]\nstruct S;\n");
+        assert_eq!(
+            html.matches("<span class=\"hlrs-Attribute\">doc</span>")
+                .count(),
+            2
+        );
+        assert!(html.contains("<span class=\"hlrs-LitStr\">\"explicit\"</span>"));
+        assert!(html.contains("<span class=\"hlrs-Comment hlrs-DocComment\">/// Sugared.</span>"));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    highlighter::{RustHighlighter, escape_html},
+    tokens::TokenTag,
+};
+
+/// How a line of a code block is shown.
+enum LineKind<'l> {
+    /// `# code`, shown only when hidden lines are revealed.
+    Hidden(&'l str),
+    /// `## code`, an escaped `#` shown as `# code`.
+    Escaped(&'l str),
+    Shown,
+}
+
+/// Classify a line the way mdbook does for Rust code blocks.
+///
+/// A line is hidden when its first non whitespace character is a `#`
+/// followed by a space or the end of the line, so attributes such as
+/// `#[derive(Debug)]` and `#![allow(unused)]` are never hidden.
+fn line_kind(line: &str) -> LineKind<'_> {
+    let Some(after_hash) = line.trim_start().strip_prefix('#') else {
+        return LineKind::Shown;
+    };
+    if after_hash.starts_with('#') {
+        return LineKind::Escaped(after_hash);
+    }
+    match after_hash.strip_prefix(' ') {
+        Some(code) => LineKind::Hidden(code),
+        None if after_hash.trim_end_matches(['\n', '\r']).is_empty() => {
+            LineKind::Hidden(after_hash)
+        }
+        None => LineKind::Shown,
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Strip the markers of hidden lines, and register every run of
+    /// consecutive hidden lines, along with their line breaks, as a single
+    /// boring region.
+    ///
+    /// The returned code is the one that is parsed and highlighted, so the
+    /// regions are registered at its offsets.
+    pub(crate) fn register_boring(&mut self, code: &str) -> String {
+        let mut output = String::with_capacity(code.len());
+        // The start and number of lines of the region being hidden.
+        let mut region: Option<(usize, usize)> = None;
+        for line in code.split_inclusive('\n') {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let kind = line_kind(line);
+            if !matches!(kind, LineKind::Hidden(_))
+                && let Some((start, lines)) = region.take()
+            {
+                self.register_boring_region(start, output.len(), lines);
+            }
+            match kind {
+                LineKind::Hidden(code) => {
+                    let (_, lines) = region.get_or_insert((output.len(), 0));
+                    *lines += 1;
+                    output.push_str(indent);
+                    output.push_str(code);
+                }
+                LineKind::Escaped(code) => {
+                    output.push_str(indent);
+                    output.push_str(code);
+                }
+                LineKind::Shown => output.push_str(line),
+            }
+        }
+        if let Some((start, lines)) = region {
+            self.register_boring_region(start, output.len(), lines);
+        }
+        output
+    }
+
+    fn register_boring_region(&mut self, start: usize, end: usize, lines: usize) {
+        self.boring_regions.insert(start, lines);
+        self.register_tag_at_index(start, end, TokenTag::Boring);
+    }
+
+    /// The opening of the hidden region starting at `start`, summarized by
+    /// the configured placeholder until it is expanded.
+    pub(crate) fn boring_region_opening(&self, start: usize) -> String {
+        let lines = self.boring_regions.get(&start).copied().unwrap_or_default();
+        let placeholder = escape_html(
+            &self
+                .config
+                .hidden_placeholder
+                .replace("{}", &lines.to_string()),
+        );
+        let open = if self.config.show_hidden { " open" } else { "" };
+        format!("<details class=\"boring\"{open}><summary>{placeholder}</summary>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        highlighter::{RustHighlighter, highlight},
+        preprocessor::HighlightConfig,
+    };
+
+    /// Assert that every `<span>` and `<details>` of the highlighted code is
+    /// closed inside the element it was opened in.
+    fn assert_nested(html: &str) {
+        let mut open = Vec::new();
+        let mut rest = html;
+        while let Some(tag_start) = rest.find('<') {
+            let tag_end = tag_start + rest[tag_start..].find('>').expect("unclosed tag");
+            let tag = &rest[tag_start + 1..tag_end];
+            let name = tag.split(' ').next().unwrap();
+            match name.strip_prefix('/') {
+                Some(closed) => assert_eq!(open.pop(), Some(closed), "misnested in {html}"),
+                None => open.push(name),
+            }
+            rest = &rest[tag_end + 1..];
+        }
+        assert!(open.is_empty(), "unclosed {open:?} in {html}");
+    }
+
+    /// The code shown once every hidden region is expanded, without the
+    /// placeholders summarizing them, and with its text unescaped.
+    fn revealed(html: &str) -> String {
+        let mut code = String::new();
+        let mut rest = html;
+        while let Some(tag_start) = rest.find('<') {
+            code.push_str(&rest[..tag_start]);
+            rest = match rest[tag_start..].starts_with("<summary>") {
+                true => &rest[tag_start + rest[tag_start..].find("</summary>").unwrap()..],
+                false => &rest[tag_start..],
+            };
+            rest = &rest[rest.find('>').unwrap() + 1..];
+        }
+        code.push_str(rest);
+        code.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&")
+    }
+
+    #[test]
+    fn hides_only_leading_markers() {
+        let html = highlight(
+            "# fn main() {\n    let s = \"# shown\";\n#\n    let t = \"\n## escaped\";\n# }\n",
+        );
+        assert_nested(&html);
+        assert_eq!(
+            revealed(&html),
+            "fn main() {\n    let s = \"# shown\";\n\n    let t = \"\n# escaped\";\n}\n"
+        );
+        assert_eq!(html.matches("<details").count(), 3);
+    }
+
+    #[test]
+    fn markup_in_code_is_escaped() {
+        let code = "fn main() {\n# let s = \"a < b </span></details>\";\n    let v: Vec<&str> = vec![s];\n}\n";
+        let html = highlight(code);
+        assert_nested(&html);
+        assert!(html.contains("a &lt; b &lt;/span&gt;&lt;/details&gt;"));
+        assert!(html.contains("&amp;"));
+        assert_eq!(revealed(&html), code.replace("# ", ""));
+    }
+
+    #[test]
+    fn placeholder_is_escaped() {
+        let config = HighlightConfig {
+            hidden_placeholder: String::from("<{} lines & more>"),
+            ..HighlightConfig::default()
+        };
+        let html = RustHighlighter::highlight(
+            "# use std::fmt;\nfn main() {}\n",
+            0,
+            &mut HashMap::new(),
+            &config,
+        );
+        assert_nested(&html);
+        assert!(html.contains("<summary>&lt;1 lines &amp; more&gt;</summary>"));
+    }
+
+    #[test]
+    fn never_hides_attributes() {
+        let html = highlight("#![allow(unused)]\n#[derive(Debug)]\nstruct Unit;\n");
+        assert!(!html.contains("boring"));
+    }
+
+    #[test]
+    fn merges_consecutive_hidden_lines() {
+        let html = highlight("# use std::fmt;\n# use std::io;\nfn main() {}\n# fn other() {}");
+        assert_eq!(html.matches("<details").count(), 2);
+        assert!(html.contains("// hidden lines: 2"));
+    }
+
+    #[test]
+    fn region_starting_mid_expression_is_highlighted() {
+        let html = highlight("fn main() {\n    let total = 1 +\n#       2 +\n        3;\n}\n");
+        assert_nested(&html);
+        let region = &html[html.find("<details").unwrap()..html.find("</details>").unwrap()];
+        assert!(region.contains("<span class=\"hlrs-LitNum\">2</span>"));
+    }
+
+    #[test]
+    fn region_ending_mid_expression_is_highlighted() {
+        let html = highlight("fn main() {\n#   let total = foo(\n#       1,\n        2);\n}\n");
+        assert_nested(&html);
+        let region = &html[html.find("<details").unwrap()..html.find("</details>").unwrap()];
+        assert!(region.contains("<span class=\"hlrs-Keyword hlrs-KeywordStorage\">let</span>"));
+        assert!(region.contains("<span class=\"hlrs-LitNum\">1</span>"));
+    }
+
+    #[test]
+    fn string_crossing_region_edges_is_split() {
+        for code in [
+            "fn main() {\n# let s = \"hidden\n    shown\";\n}\n",
+            "fn main() {\n    let s = \"shown\n# hidden\";\n# let t = 1;\n}\n",
+            "fn main() {\n    let s = \"shown\n# hidden\n    shown\";\n}\n",
+        ] {
+            let html = highlight(code);
+            assert_nested(&html);
+            assert_eq!(revealed(&html), code.replace("# ", ""));
+        }
+    }
+
+    #[test]
+    fn block_comment_crossing_region_edge_is_split() {
+        let html = highlight("/* shown\n# hidden */\nfn main() {}\n");
+        assert_nested(&html);
+        assert!(html.contains("<span class=\"hlrs-Comment\">hidden */</span>\n</details>"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::ops::Range;
+
+use crate::{
+    highlighter::{RustHighlighter, doc::is_doc_comment},
+    tokens::TokenTag,
+};
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()
+}
+
+/// The end of the quoted literal whose opening `quote` is at `start`,
+/// skipping escaped characters.
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            byte if byte == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// The end of the raw string whose `#`s or `"` start at `start`, or `None`
+/// if they don't open a raw string, as in the raw identifier `r#type`.
+fn skip_raw(code: &str, start: usize) -> Option<usize> {
+    let hashes = code[start..]
+        .bytes()
+        .take_while(|byte| *byte == b'#')
+        .count();
+    if code.as_bytes().get(start + hashes) != Some(&b'"') {
+        return None;
+    }
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    let body = start + hashes + 1;
+    Some(
+        code[body..]
+            .find(&terminator)
+            .map_or(code.len(), |end| body + end + terminator.len()),
+    )
+}
+
+/// The end of the block comment starting at `start`, which may nest.
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'*')) => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', Some(b'/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Find the byte ranges of every comment in `code`.
+///
+/// Literals are lexed just enough to be skipped, so `//` inside a string or
+/// a `'/'` char never starts a comment.
+pub(crate) fn comment_ranges(code: &str) -> Vec<Range<usize>> {
+    let bytes = code.as_bytes();
+    let mut comments = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                let end = code[i..].find('\n').map_or(code.len(), |end| i + end);
+                comments.push(i..end);
+                i = end;
+            }
+            (b'/', Some(b'*')) => {
+                let end = skip_block_comment(bytes, i);
+                comments.push(i..end);
+                i = end;
+            }
+            (b'"', _) => i = skip_quoted(bytes, i, b'"'),
+            (b'\'', Some(b'\\')) => i = skip_quoted(bytes, i, b'\''),
+            (b'\'', Some(_)) => {
+                // A char literal closes right after its character, a
+                // lifetime or label doesn't.
+                let len = code[i + 1..].chars().next().map_or(1, char::len_utf8);
+                i += match bytes.get(i + 1 + len) {
+                    Some(b'\'') => len + 2,
+                    _ => 1,
+                };
+            }
+            (byte, _) if is_ident_byte(byte) => {
+                let start = i;
+                while i < bytes.len() && is_ident_byte(bytes[i]) {
+                    i += 1;
+                }
+                // Literal prefixes, as in `b"..."`, `br#"..."#` and `b'x'`.
+                match (&code[start..i], bytes.get(i)) {
+                    ("r" | "br" | "cr", Some(b'"' | b'#')) => {
+                        if let Some(end) = skip_raw(code, i) {
+                            i = end;
+                        }
+                    }
+                    ("b" | "c", Some(b'"')) => i = skip_quoted(bytes, i, b'"'),
+                    ("b", Some(b'\'')) => i = skip_quoted(bytes, i, b'\''),
+                    _ => {}
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    comments
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    pub(crate) fn register_comments(&mut self, code: &str) {
+        // The lines of consecutive doc comments, whose Markdown is
+        // registered as a whole.
+        let mut doc_lines = Vec::new();
+        let mut previous_end = 0;
+        for comment in comment_ranges(code) {
+            let text = &code[comment.clone()];
+            if !code[previous_end..comment.start].trim().is_empty() {
+                self.register_doc_lines(&mut doc_lines);
+            }
+            previous_end = comment.end;
+
+            if !is_doc_comment(text) {
+                self.register_doc_lines(&mut doc_lines);
+                self.register_tag_at_index(comment.start, comment.end, TokenTag::Comment);
+                continue;
+            }
+            self.register_tag_at_index(comment.start, comment.end, TokenTag::DocComment);
+            if text.starts_with("//") {
+                doc_lines.push((comment.start + 3, &text[3..]));
+                continue;
+            }
+            // Block doc comments may start their lines with a `*`.
+            let body = &text[3..text.len().saturating_sub(2).max(3)];
+            let mut offset = comment.start + 3;
+            for line in body.split_inclusive('\n') {
+                let content = line.trim_start();
+                let content = content.strip_prefix('*').unwrap_or(content);
+                let start = offset + line.len() - content.len();
+                doc_lines.push((start, content.trim_end_matches('\n')));
+                offset += line.len();
+            }
+            self.register_doc_lines(&mut doc_lines);
+        }
+        self.register_doc_lines(&mut doc_lines);
+    }
+
+    fn register_doc_lines(&mut self, lines: &mut Vec<(usize, &str)>) {
+        if self.config.doc_markdown && !lines.is_empty() {
+            self.register_doc_markdown(lines);
+        }
+        lines.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::comment_ranges;
+
+    fn comments(code: &str) -> Vec<&str> {
+        comment_ranges(code)
+            .into_iter()
+            .map(|range| &code[range])
+            .collect()
+    }
+
+    #[test]
+    fn slashes_in_strings_are_not_comments() {
+        assert_eq!(
+            comments("let url = \"https://example.com\"; // link\n"),
+            ["// link"]
+        );
+        assert_eq!(comments("let s = \"\\\" // quoted\";"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert_eq!(
+            comments("a /* outer /* inner */ still */ b /* c */"),
+            ["/* outer /* inner */ still */", "/* c */"]
+        );
+    }
+
+    #[test]
+    fn raw_strings_are_skipped() {
+        assert_eq!(
+            comments("let s = r#\"// \"quoted\" /* \"#; // after\n"),
+            ["// after"]
+        );
+        assert_eq!(comments("let s = br\"/*\"; /**/"), ["/**/"]);
+        // A raw identifier doesn't open a raw string.
+        assert_eq!(comments("let r#type = 1; // kept"), ["// kept"]);
+    }
+
+    #[test]
+    fn chars_are_skipped() {
+        assert_eq!(comments("let c = '/'; // slash"), ["// slash"]);
+        assert_eq!(comments("let c = '\\''; // quote"), ["// quote"]);
+        assert_eq!(comments("let c = b'\"'; // byte"), ["// byte"]);
+        // Lifetimes and labels don't open a char literal.
+        assert_eq!(
+            comments("fn f<'a>(x: &'a str) {} // lifetime"),
+            ["// lifetime"]
+        );
+    }
+}
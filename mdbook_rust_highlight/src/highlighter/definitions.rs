@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use syn::{
+    ImplItemConst, ImplItemFn, Item, ItemConst, ItemEnum, ItemStatic, ItemStruct, ItemTrait,
+    ItemType, ItemUnion, TraitItemConst, TraitItemFn, TraitItemType, visit::Visit,
+};
+
+use crate::{highlighter::modifier::is_deprecated, tokens::TokenTag};
+
+/// Names defined by a snippet, collected before highlighting so that uses
+/// preceding a definition are tagged by the kind of item they name.
+#[derive(Default)]
+pub(crate) struct Definitions {
+    pub(crate) items: Vec<(String, TokenTag)>,
+    /// Names of the items marked `#[deprecated]`.
+    pub(crate) deprecated: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for Definitions {
+    fn visit_item(&mut self, i: &'ast Item) {
+        let deprecated = match i {
+            Item::Const(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            Item::Enum(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            Item::Fn(item) if is_deprecated(&item.attrs) => Some(&item.sig.ident),
+            Item::Static(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            Item::Struct(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            Item::Trait(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            Item::Type(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            Item::Union(item) if is_deprecated(&item.attrs) => Some(&item.ident),
+            _ => None,
+        };
+        if let Some(ident) = deprecated {
+            self.deprecated.insert(ident.to_string());
+        }
+        syn::visit::visit_item(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast ImplItemFn) {
+        if is_deprecated(&i.attrs) {
+            self.deprecated.insert(i.sig.ident.to_string());
+        }
+        syn::visit::visit_impl_item_fn(self, i);
+    }
+
+    fn visit_trait_item_fn(&mut self, i: &'ast TraitItemFn) {
+        if is_deprecated(&i.attrs) {
+            self.deprecated.insert(i.sig.ident.to_string());
+        }
+        syn::visit::visit_trait_item_fn(self, i);
+    }
+
+    fn visit_item_struct(&mut self, i: &'ast ItemStruct) {
+        self.items.push((i.ident.to_string(), TokenTag::Struct));
+        syn::visit::visit_item_struct(self, i);
+    }
+
+    fn visit_item_enum(&mut self, i: &'ast ItemEnum) {
+        self.items.push((i.ident.to_string(), TokenTag::Enum));
+        for variant in &i.variants {
+            self.items
+                .push((variant.ident.to_string(), TokenTag::Variant));
+        }
+        syn::visit::visit_item_enum(self, i);
+    }
+
+    fn visit_item_union(&mut self, i: &'ast ItemUnion) {
+        self.items.push((i.ident.to_string(), TokenTag::Union));
+        syn::visit::visit_item_union(self, i);
+    }
+
+    fn visit_item_trait(&mut self, i: &'ast ItemTrait) {
+        self.items.push((i.ident.to_string(), TokenTag::Trait));
+        syn::visit::visit_item_trait(self, i);
+    }
+
+    fn visit_item_const(&mut self, i: &'ast ItemConst) {
+        self.items.push((i.ident.to_string(), TokenTag::Constant));
+        syn::visit::visit_item_const(self, i);
+    }
+
+    fn visit_item_static(&mut self, i: &'ast ItemStatic) {
+        self.items.push((i.ident.to_string(), TokenTag::Static));
+        syn::visit::visit_item_static(self, i);
+    }
+
+    fn visit_item_type(&mut self, i: &'ast ItemType) {
+        self.items.push((i.ident.to_string(), TokenTag::TypeAlias));
+        syn::visit::visit_item_type(self, i);
+    }
+
+    fn visit_trait_item_type(&mut self, i: &'ast TraitItemType) {
+        self.items.push((i.ident.to_string(), TokenTag::TypeAlias));
+        syn::visit::visit_trait_item_type(self, i);
+    }
+
+    fn visit_impl_item_const(&mut self, i: &'ast ImplItemConst) {
+        self.items.push((i.ident.to_string(), TokenTag::Constant));
+        syn::visit::visit_impl_item_const(self, i);
+    }
+
+    fn visit_trait_item_const(&mut self, i: &'ast TraitItemConst) {
+        self.items.push((i.ident.to_string(), TokenTag::Constant));
+        syn::visit::visit_trait_item_const(self, i);
+    }
+}
@@ -0,0 +1,318 @@
+use std::ops::Range;
+
+use proc_macro2::TokenStream;
+use syn::{File, visit::Visit};
+
+use crate::{
+    highlighter::{RustHighlighter, macros::BuiltinMacros, modifier::is_library_name},
+    tokens::{SpannedToken, TokenTag},
+};
+
+/// Attributes of a doctest fence, as in ```` ```should_panic,edition2021 ````.
+const DOCTEST_ATTRIBUTES: &[&str] = &[
+    "rust",
+    "hlrs",
+    "ignore",
+    "should_panic",
+    "no_run",
+    "compile_fail",
+    "test_harness",
+    "standalone_crate",
+];
+
+/// Whether a comment is a `///`, `//!`, `/**` or `/*!` doc comment, whose
+/// text starts after these three characters.
+pub(crate) fn is_doc_comment(comment: &str) -> bool {
+    match comment.get(..3) {
+        Some("//!" | "/*!") => true,
+        Some("///") => !comment.starts_with("////"),
+        Some("/**") => !comment.starts_with("/***") && comment != "/**/",
+        _ => false,
+    }
+}
+
+/// Whether a fence with this info string holds Rust code.
+fn is_rust_fence(info: &str) -> bool {
+    info.split([',', ' ', '\t'])
+        .filter(|attribute| !attribute.is_empty())
+        .all(|attribute| {
+            DOCTEST_ATTRIBUTES.contains(&attribute) || attribute.starts_with("edition")
+        })
+}
+
+/// Whether a link label or destination is an intra-doc link to a Rust path.
+fn is_rust_path(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '<' | '>' | '!' | '(' | ')'))
+}
+
+/// The documentation of the standard library an intra-doc link points to, as
+/// a search for its path, since the module of a name isn't known.
+fn std_doc_link(target: &str) -> Option<String> {
+    let target = target.trim_matches('`');
+    if !is_rust_path(target) {
+        return None;
+    }
+    // `Vec::new()`, `vec!` and `Vec<T>` all point to the item itself.
+    let path = &target[..target.find(['(', '!', '<']).unwrap_or(target.len())];
+    let first = path.split("::").next()?;
+    let last = path.rsplit("::").next()?;
+    (matches!(first, "std" | "core" | "alloc") || is_library_name(last))
+        .then(|| format!("https://doc.rust-lang.org/std/?search={path}"))
+}
+
+/// The end of the bracketed text whose `open` delimiter is at `start`.
+fn matching_close(text: &str, start: usize, open: u8, close: u8) -> Option<usize> {
+    let mut depth = 0;
+    for (i, byte) in text.bytes().enumerate().skip(start) {
+        if byte == open {
+            depth += 1;
+        } else if byte == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i + 1);
+            }
+        }
+    }
+    None
+}
+
+/// Rust code gathered from a doc comment, with the offset in the highlighted
+/// code every piece of it was taken from.
+#[derive(Default)]
+struct Snippet {
+    code: String,
+    pieces: Vec<(Range<usize>, usize)>,
+}
+
+impl Snippet {
+    fn push(&mut self, text: &str, offset: usize) {
+        let start = self.code.len();
+        self.code.push_str(text);
+        self.pieces.push((start..self.code.len(), offset));
+    }
+
+    /// The offset in the highlighted code of `index` in the snippet, or of
+    /// the end of the last piece before it, if there is one.
+    fn position(&self, index: usize) -> Option<usize> {
+        let piece = self
+            .pieces
+            .partition_point(|(range, _)| range.start <= index);
+        let (range, offset) = self.pieces.get(piece.checked_sub(1)?)?;
+        Some(offset + index.min(range.end) - range.start)
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Register the Markdown of consecutive doc comment lines, each given
+    /// with the offset of its text after the `///`.
+    pub(crate) fn register_doc_markdown(&mut self, lines: &[(usize, &str)]) {
+        let mut fence: Option<(bool, Snippet)> = None;
+        for &(offset, text) in lines {
+            let trimmed = text.trim_start();
+            let indent = text.len() - trimmed.len();
+            if let Some(info) = trimmed.strip_prefix("```") {
+                self.register_tag_at_index(
+                    offset + indent,
+                    offset + text.len(),
+                    TokenTag::DocFence,
+                );
+                match fence.take() {
+                    Some((true, doctest)) => self.register_doctest(doctest),
+                    Some((false, _)) => {}
+                    None => fence = Some((is_rust_fence(info.trim()), Snippet::default())),
+                }
+            } else if let Some((_, doctest)) = &mut fence {
+                // Lines hidden from the rendered doctest still belong to it.
+                match trimmed.strip_prefix("# ") {
+                    Some(line) => doctest.push(line, offset + text.len() - line.len()),
+                    None => doctest.push(text, offset),
+                }
+                doctest.push("\n", offset + text.len());
+            } else {
+                let heading = trimmed.trim_start_matches('#');
+                if heading.len() < trimmed.len() && (heading.is_empty() || heading.starts_with(' '))
+                {
+                    self.register_tag_at_index(
+                        offset + indent,
+                        offset + text.len(),
+                        TokenTag::DocHeading,
+                    );
+                }
+                self.register_doc_inline(offset, text);
+            }
+        }
+        if let Some((true, doctest)) = fence {
+            self.register_doctest(doctest);
+        }
+    }
+
+    /// Register the inline code and links of a line of Markdown.
+    fn register_doc_inline(&mut self, offset: usize, text: &str) {
+        let bytes = text.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'`' => {
+                    let ticks = text[i..].bytes().take_while(|byte| *byte == b'`').count();
+                    let fence = &text[i..i + ticks];
+                    let Some(close) = text[i + ticks..].find(fence) else {
+                        i += ticks;
+                        continue;
+                    };
+                    let code_start = i + ticks;
+                    let code_end = code_start + close;
+                    let end = code_end + ticks;
+                    self.register_tag_at_index(offset + i, offset + end, TokenTag::DocCode);
+                    let mut snippet = Snippet::default();
+                    snippet.push(&text[code_start..code_end], offset + code_start);
+                    self.register_rust_snippet(&snippet);
+                    i = end;
+                }
+                b'[' => {
+                    let Some(label_end) = matching_close(text, i, b'[', b']') else {
+                        i += 1;
+                        continue;
+                    };
+                    let label = &text[i + 1..label_end - 1];
+                    let destination = match bytes.get(label_end) {
+                        Some(b'(') => matching_close(text, label_end, b'(', b')'),
+                        Some(b'[') => matching_close(text, label_end, b'[', b']'),
+                        _ => None,
+                    };
+                    let end = destination.unwrap_or(label_end);
+                    self.register_tag_at_index(offset + i, offset + end, TokenTag::DocLink);
+                    let target = match destination {
+                        Some(destination) => &text[label_end + 1..destination - 1],
+                        None => label,
+                    };
+                    if let Some(href) = std_doc_link(target) {
+                        self.doc_links.insert(offset + i, href);
+                    }
+                    match destination {
+                        Some(_) => {
+                            if is_rust_path(target) {
+                                let mut snippet = Snippet::default();
+                                snippet.push(target, offset + label_end + 1);
+                                self.register_rust_snippet(&snippet);
+                            }
+                            self.register_doc_inline(offset + i + 1, label);
+                        }
+                        None if is_rust_path(label) => {
+                            let mut snippet = Snippet::default();
+                            snippet.push(label, offset + i + 1);
+                            self.register_rust_snippet(&snippet);
+                        }
+                        None => self.register_doc_inline(offset + i + 1, label),
+                    }
+                    i = end;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Register a doctest, parsed as a file when it can be, and wrapped in a
+    /// `main` function when its statements can't stand on their own.
+    fn register_doctest(&mut self, doctest: Snippet) {
+        if syn::parse_str::<File>(&doctest.code).is_ok() {
+            return self.register_rust_snippet(&doctest);
+        }
+        let mut wrapped = Snippet::default();
+        wrapped.code.push_str("fn main() {\n");
+        for (range, offset) in &doctest.pieces {
+            wrapped.push(&doctest.code[range.clone()], *offset);
+        }
+        wrapped.code.push_str("}\n");
+        self.register_rust_snippet(&wrapped);
+    }
+
+    /// Highlight a snippet of Rust with a highlighter of its own, and move
+    /// its tokens to where the snippet was taken from.
+    ///
+    /// Snippets that don't parse as a file are classified lexically.
+    fn register_rust_snippet(&mut self, snippet: &Snippet) {
+        let syntax_tree = syn::parse_str::<File>(&snippet.code).ok();
+        let mut macros = BuiltinMacros::default();
+        if let Some(syntax_tree) = &syntax_tree {
+            macros.visit_file(syntax_tree);
+        }
+        let mut highlighter = RustHighlighter::new(&mut *self.ident_map, self.config);
+        match &syntax_tree {
+            Some(syntax_tree) => highlighter.register_file(syntax_tree, &macros),
+            None => match snippet.code.parse::<TokenStream>() {
+                Ok(tokens) => highlighter.register_token_stream(tokens),
+                Err(_) => return,
+            },
+        }
+        highlighter.register_comments(&snippet.code);
+        highlighter.attach_modifiers();
+
+        let tokens: Vec<_> = std::mem::take(&mut highlighter.token_set)
+            .into_iter()
+            .filter_map(|token| highlighter.identified(token))
+            .collect();
+        let doc_links = std::mem::take(&mut highlighter.doc_links);
+        drop(highlighter);
+
+        for (start, href) in doc_links {
+            if let Some(start) = snippet.position(start) {
+                self.doc_links.insert(start, href);
+            }
+        }
+
+        // Tags of the `main` wrapping a doctest are dropped.
+        for token in tokens {
+            let Some(start) = snippet.position(token.start) else {
+                continue;
+            };
+            let end = snippet.position(token.end).unwrap_or(start);
+            self.register_token(SpannedToken {
+                start,
+                end,
+                ..token
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::std_doc_link;
+    use crate::{highlighter::RustHighlighter, preprocessor::HighlightConfig};
+
+    #[test]
+    fn library_links_point_to_std_docs() {
+        assert_eq!(
+            std_doc_link("`Vec`").as_deref(),
+            Some("https://doc.rust-lang.org/std/?search=Vec")
+        );
+        assert_eq!(
+            std_doc_link("std::mem::take()").as_deref(),
+            Some("https://doc.rust-lang.org/std/?search=std::mem::take")
+        );
+        assert_eq!(std_doc_link("Snippet"), None);
+        assert_eq!(std_doc_link("https://example.com"), None);
+    }
+
+    #[test]
+    fn library_links_are_anchors() {
+        let config = HighlightConfig {
+            doc_markdown: true,
+            ..HighlightConfig::default()
+        };
+        let html = RustHighlighter::highlight(
+            "/// Like [`Vec`], unlike [Snippet].\nstruct S;\n",
+            0,
+            &mut HashMap::new(),
+            &config,
+        );
+        assert!(html.contains("href=\"https://doc.rust-lang.org/std/?search=Vec\">["));
+        assert_eq!(html.matches("<a ").count(), html.matches("</a>").count());
+        assert_eq!(html.matches("<a ").count(), 1);
+    }
+}
@@ -1,11 +1,16 @@
 use mdbook_rust_highlight_derive::add_try_method;
 use syn::{
-    Arm, Expr, ExprBinary, ExprBlock, ExprCall, ExprCast, ExprField, ExprForLoop, ExprIf, ExprLit,
-    ExprMatch, ExprMethodCall, ExprParen, ExprPath, ExprReference, ExprTry, ExprTuple, ExprUnary,
-    ExprUnsafe, Lit, Member, spanned::Spanned,
+    Arm, BinOp, Expr, ExprAssign, ExprBinary, ExprBlock, ExprBreak, ExprCall, ExprCast,
+    ExprClosure, ExprConst, ExprContinue, ExprField, ExprForLoop, ExprIf, ExprLet, ExprLit,
+    ExprLoop, ExprMatch, ExprMethodCall, ExprParen, ExprPath, ExprRange, ExprReference, ExprReturn,
+    ExprStruct, ExprTry, ExprTuple, ExprUnary, ExprUnsafe, ExprWhile, Ident, Member, RangeLimits,
+    UnOp, spanned::Spanned,
 };
 
-use crate::highlighter::RustHighlighter;
+use crate::{
+    highlighter::RustHighlighter,
+    tokens::{Modifier, TokenTag},
+};
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     #[add_try_method]
@@ -18,6 +23,21 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
             Expr::ForLoop(token) => {
                 self.register_for_loop_expr(token);
             }
+            Expr::Loop(token) => {
+                self.register_loop_expr(token);
+            }
+            Expr::While(token) => {
+                self.register_while_expr(token);
+            }
+            Expr::Break(token) => {
+                self.register_break_expr(token);
+            }
+            Expr::Continue(token) => {
+                self.register_continue_expr(token);
+            }
+            Expr::Return(token) => {
+                self.register_return_expr(token);
+            }
             Expr::Unsafe(token) => {
                 self.register_unsafe_expr(token);
             }
@@ -63,31 +83,82 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
             Expr::Tuple(token) => {
                 self.register_tuple_expr(token);
             }
-            _ => {}
-        }
-    }
-
-    pub(crate) fn register_lit_expr(&mut self, token: &'ast ExprLit) {
-        match &token.lit {
-            Lit::Int(_) | Lit::Float(_) => {
-                self.register_litnum_tag(&token.lit);
+            Expr::Assign(token) => {
+                self.register_assign_expr(token);
+            }
+            Expr::Range(token) => {
+                self.register_range_expr(token);
             }
-            Lit::Bool(_) => {
-                self.register_litbool_tag(&token.lit);
+            Expr::Struct(token) => {
+                self.register_struct_expr(token);
             }
-            Lit::Byte(_) | Lit::ByteStr(_) | Lit::CStr(_) | Lit::Char(_) | Lit::Str(_) => {
-                self.register_litstr_tag(&token.lit)
+            Expr::Const(token) => {
+                self.register_const_expr(token);
+            }
+            Expr::Macro(token) => {
+                self.register_macro(&token.mac);
+            }
+            Expr::Let(token) => {
+                self.register_let_expr(token);
+            }
+            Expr::Closure(token) => {
+                self.register_closure_expr(token);
             }
             _ => {}
         }
     }
 
+    pub(crate) fn register_lit_expr(&mut self, token: &'ast ExprLit) {
+        self.register_lit(&token.lit);
+    }
+
     pub(crate) fn register_for_loop_expr(&mut self, token: &'ast ExprForLoop) {
-        self.register_keyword_tag(&token.for_token);
-        self.register_pat(&token.pat);
-        self.register_keyword_tag(&token.in_token);
+        self.register_keywordcontrol_tag(&token.for_token);
+        self.register_keywordcontrol_tag(&token.in_token);
         self.register_expr(&token.expr);
+        self.enter_scope(TokenTag::Local);
+        self.try_register_label(token.label.as_ref());
+        self.register_pat(&token.pat);
+        self.register_block(&token.body);
+        self.exit_scope();
+    }
+
+    pub(crate) fn register_loop_expr(&mut self, token: &'ast ExprLoop) {
+        self.enter_scope(TokenTag::Local);
+        self.try_register_label(token.label.as_ref());
+        self.register_keywordcontrol_tag(&token.loop_token);
+        self.register_block(&token.body);
+        self.exit_scope();
+    }
+
+    pub(crate) fn register_while_expr(&mut self, token: &'ast ExprWhile) {
+        self.enter_scope(TokenTag::Local);
+        self.try_register_label(token.label.as_ref());
+        self.register_keywordcontrol_tag(&token.while_token);
+        self.register_expr(&token.cond);
         self.register_block(&token.body);
+        self.exit_scope();
+    }
+
+    pub(crate) fn register_break_expr(&mut self, token: &'ast ExprBreak) {
+        self.register_keywordcontrol_tag(&token.break_token);
+        self.try_register_label_use(token.label.as_ref());
+        self.try_register_expr(token.expr.as_deref());
+    }
+
+    pub(crate) fn register_return_expr(&mut self, token: &'ast ExprReturn) {
+        self.register_keywordcontrol_tag(&token.return_token);
+        self.try_register_expr(token.expr.as_deref());
+    }
+
+    pub(crate) fn register_continue_expr(&mut self, token: &'ast ExprContinue) {
+        self.register_keywordcontrol_tag(&token.continue_token);
+        self.try_register_label_use(token.label.as_ref());
+    }
+
+    pub(crate) fn register_const_expr(&mut self, token: &'ast ExprConst) {
+        self.register_keywordstorage_tag(&token.const_token);
+        self.register_block(&token.block);
     }
 
     pub(crate) fn register_unsafe_expr(&mut self, token: &'ast ExprUnsafe) {
@@ -98,51 +169,203 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_method_call_expr(&mut self, token: &'ast ExprMethodCall) {
         self.register_expr(&token.receiver);
         self.register_function_tag(&token.method);
+        // The type of the receiver is only known for `self`.
+        if let Expr::Path(receiver) = &*token.receiver
+            && let Some(receiver) = receiver.path.get_ident()
+            && receiver == "self"
+            && self.is_unsafe_method(&Ident::new("Self", receiver.span()), &token.method)
+        {
+            self.register_modifier(&token.method, Modifier::Unsafe);
+        }
+        self.register_name_modifiers(&token.method);
+        self.try_register_angle_brackets_arg(token.turbofish.as_ref());
         for arg in &token.args {
             self.register_expr(arg);
         }
     }
 
     pub(crate) fn register_path_expr(&mut self, token: &'ast ExprPath) {
+        if token.qself.is_none()
+            && let Some(ident) = token.path.get_ident()
+            && self.register_value_name(ident)
+        {
+            return;
+        }
         self.try_register_qself(token.qself.as_ref());
         self.register_path(&token.path, None);
     }
 
+    /// Register a name read as a value when it is a binding, returning
+    /// whether it is one, or add the modifiers of a mutable static.
+    fn register_value_name(&mut self, ident: &Ident) -> bool {
+        if let Some(binding) = self.resolve_binding(ident) {
+            self.register_binding(ident, binding);
+            return true;
+        }
+        if self
+            .unsafe_items
+            .mutable_statics
+            .contains(&ident.to_string())
+        {
+            self.register_modifier(ident, Modifier::Unsafe);
+            self.register_modifier(ident, Modifier::Static);
+            self.register_modifier(ident, Modifier::Mutable);
+        }
+        false
+    }
+
     pub(crate) fn register_reference_expr(&mut self, token: &'ast ExprReference) {
-        self.try_register_keyword_tag(token.mutability.as_ref());
+        self.register_operatorborrow_tag(&token.and_token);
+        self.try_register_keywordstorage_tag(token.mutability.as_ref());
         self.register_expr(&token.expr);
     }
 
     pub(crate) fn register_unary_expr(&mut self, token: &'ast ExprUnary) {
+        if let UnOp::Deref(_) = token.op
+            && self.is_raw_pointer(&token.expr)
+        {
+            self.register_modifier(&token.op, Modifier::Unsafe);
+        }
+        self.register_unary_operator(&token.op);
         self.register_expr(&token.expr);
     }
 
+    pub(crate) fn register_unary_operator(&mut self, token: &'ast UnOp) {
+        match token {
+            UnOp::Deref(_) => self.register_operatorderef_tag(token),
+            UnOp::Not(_) => self.register_operatorlogical_tag(token),
+            UnOp::Neg(_) => self.register_operatorarithmetic_tag(token),
+            _ => self.register_operator_tag(token),
+        }
+    }
+
     pub(crate) fn register_binary_expr(&mut self, token: &'ast ExprBinary) {
         self.register_expr(&token.left);
+        self.register_binary_operator(&token.op);
         self.register_expr(&token.right);
     }
 
+    pub(crate) fn register_binary_operator(&mut self, token: &'ast BinOp) {
+        match token {
+            BinOp::Add(_) | BinOp::Sub(_) | BinOp::Mul(_) | BinOp::Div(_) | BinOp::Rem(_) => {
+                self.register_operatorarithmetic_tag(token);
+            }
+            BinOp::BitXor(_)
+            | BinOp::BitAnd(_)
+            | BinOp::BitOr(_)
+            | BinOp::Shl(_)
+            | BinOp::Shr(_) => {
+                self.register_operatorbitwise_tag(token);
+            }
+            BinOp::And(_) | BinOp::Or(_) => {
+                self.register_operatorlogical_tag(token);
+            }
+            BinOp::Eq(_)
+            | BinOp::Lt(_)
+            | BinOp::Le(_)
+            | BinOp::Ne(_)
+            | BinOp::Ge(_)
+            | BinOp::Gt(_) => {
+                self.register_operatorcomparison_tag(token);
+            }
+            BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+            | BinOp::BitXorAssign(_)
+            | BinOp::BitAndAssign(_)
+            | BinOp::BitOrAssign(_)
+            | BinOp::ShlAssign(_)
+            | BinOp::ShrAssign(_) => {
+                self.register_operatorassignment_tag(token);
+            }
+            _ => self.register_operator_tag(token),
+        }
+    }
+
+    pub(crate) fn register_assign_expr(&mut self, token: &'ast ExprAssign) {
+        self.register_expr(&token.left);
+        self.register_operatorassignment_tag(&token.eq_token);
+        self.register_expr(&token.right);
+    }
+
+    pub(crate) fn register_range_expr(&mut self, token: &'ast ExprRange) {
+        self.try_register_expr(token.start.as_deref());
+        match &token.limits {
+            RangeLimits::HalfOpen(limits) => self.register_operator_tag(limits),
+            RangeLimits::Closed(limits) => self.register_operator_tag(limits),
+        }
+        self.try_register_expr(token.end.as_deref());
+    }
+
     pub(crate) fn register_try_expr(&mut self, token: &'ast ExprTry) {
         self.register_expr(&token.expr);
+        self.register_operatortry_tag(&token.question_token);
     }
 
     pub(crate) fn register_if_expr(&mut self, token: &'ast ExprIf) {
-        self.register_keyword_tag(&token.if_token);
+        self.register_keywordcontrol_tag(&token.if_token);
+        // The bindings of an `if let` are visible in the then branch only.
+        self.enter_scope(TokenTag::Local);
         self.register_expr(&token.cond);
         self.register_block(&token.then_branch);
+        self.exit_scope();
         if let Some(else_block) = &token.else_branch {
-            self.register_keyword_tag(&else_block.0);
+            self.register_keywordcontrol_tag(&else_block.0);
             self.register_expr(&else_block.1);
         }
     }
 
+    /// Register the `let` of an `if let` or `while let` condition, whose
+    /// bindings are declared in the scope opened for the condition.
+    pub(crate) fn register_let_expr(&mut self, token: &'ast ExprLet) {
+        self.register_keywordstorage_tag(&token.let_token);
+        // The scrutinee can't see the bindings it is matched against.
+        self.register_expr(&token.expr);
+        self.register_pat(&token.pat);
+        self.annotate_binding(&token.pat, None, Some(&token.expr));
+    }
+
+    pub(crate) fn register_closure_expr(&mut self, token: &'ast ExprClosure) {
+        self.enter_scope(TokenTag::Parameter);
+        self.try_register_bound_lifetimes(token.lifetimes.as_ref());
+        self.try_register_keywordstorage_tag(token.constness.as_ref());
+        self.try_register_keywordstorage_tag(token.movability.as_ref());
+        self.try_register_keyword_tag(token.asyncness.as_ref());
+        self.try_register_keywordstorage_tag(token.capture.as_ref());
+        for input in &token.inputs {
+            self.register_pat(input);
+            self.annotate_binding(input, None, None);
+        }
+        self.register_return_type(&token.output);
+        self.register_expr(&token.body);
+        self.exit_scope();
+    }
+
     pub(crate) fn register_call_expr(&mut self, token: &'ast ExprCall) {
+        if let Expr::Path(func) = &*token.func {
+            let segments: Vec<_> = func.path.segments.iter().map(|s| &s.ident).collect();
+            let unsafe_call = match segments.as_slice() {
+                [name] => {
+                    self.unsafe_items.functions.contains(&name.to_string())
+                        && self.resolve_binding(name).is_none()
+                }
+                [.., owner, name] => self.is_unsafe_method(owner, name),
+                [] => false,
+            };
+            if let Some(name) = segments.last()
+                && unsafe_call
+            {
+                self.register_modifier(*name, Modifier::Unsafe);
+            }
+        }
         self.register_expr(&token.func);
         let token_position = token.span().byte_range();
         for pos in token_position.start..token_position.end {
             if let Some(unidentified) = self.unidentified.remove(&pos) {
                 if let Some(known) = self.ident_map.get(unidentified.ident.to_string().as_str()) {
-                    self.register_ident(&unidentified.ident, known.clone());
+                    self.register_tag(&unidentified.ident, *known);
                 } else {
                     self.register_function_tag(&unidentified.ident);
                 }
@@ -154,7 +377,10 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     }
 
     pub(crate) fn register_block_expr(&mut self, token: &'ast ExprBlock) {
+        self.enter_scope(TokenTag::Local);
+        self.try_register_label(token.label.as_ref());
         self.register_block(&token.block);
+        self.exit_scope();
     }
 
     pub(crate) fn register_parentheses_expr(&mut self, token: &'ast ExprParen) {
@@ -169,16 +395,37 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_field_expr(&mut self, token: &'ast ExprField) {
         self.register_expr(&token.base);
         self.register_member(&token.member);
+        if self.is_union(&token.base) {
+            self.register_modifier(&token.member, Modifier::Unsafe);
+        }
     }
 
     pub(crate) fn register_match_expr(&mut self, token: &'ast ExprMatch) {
-        self.register_keyword_tag(&token.match_token);
+        self.register_keywordcontrol_tag(&token.match_token);
         self.register_expr(&token.expr);
         for arm in &token.arms {
             self.register_arm(arm);
         }
     }
 
+    pub(crate) fn register_struct_expr(&mut self, token: &'ast ExprStruct) {
+        self.try_register_qself(token.qself.as_ref());
+        self.register_constructor_path(&token.path);
+        for field in &token.fields {
+            self.register_member(&field.member);
+            // In the `Foo { x }` shorthand the member and the expression
+            // share a span, on which the name read is registered as well.
+            match (&field.colon_token, &field.member) {
+                (Some(_), _) => self.register_expr(&field.expr),
+                (None, Member::Named(ident)) => {
+                    self.register_value_name(ident);
+                }
+                (None, Member::Unnamed(_)) => {}
+            }
+        }
+        self.try_register_expr(token.rest.as_deref());
+    }
+
     pub(crate) fn register_tuple_expr(&mut self, token: &'ast ExprTuple) {
         for arg in &token.elems {
             self.register_expr(arg);
@@ -186,18 +433,21 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     }
 
     pub(crate) fn register_arm(&mut self, token: &'ast Arm) {
+        self.enter_scope(TokenTag::Local);
         self.register_pat(&token.pat);
         if let Some(guard) = &token.guard {
-            self.register_keyword_tag(&guard.0);
+            self.register_keywordcontrol_tag(&guard.0);
             self.register_expr(&guard.1);
         }
+        self.register_operator_tag(&token.fat_arrow_token);
         self.register_expr(&token.body);
+        self.exit_scope();
     }
 
     pub(crate) fn register_member(&mut self, token: &'ast Member) {
         match token {
             Member::Named(token) => {
-                self.register_ident_tag(token);
+                self.register_field_tag(token);
             }
             Member::Unnamed(token) => {
                 self.register_litnum_tag(token);
@@ -205,3 +455,67 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn if_let_bindings_are_declared_for_the_then_branch() {
+        let html =
+            highlight("fn main() {\n    if let Some(mut x) = opt { x += 1; } else { x; }\n}\n");
+        assert!(html.contains("<span class=\"hlrs-Keyword hlrs-KeywordStorage\">let</span>"));
+        assert!(html.contains(
+            "<span class=\"hlrs-Ident hlrs-Local hlrs-mod-declaration hlrs-mod-mutable\">x</span>"
+        ));
+        assert!(html.contains("{ <span class=\"hlrs-Ident hlrs-Local hlrs-mod-mutable\">x</span>"));
+        assert!(html.contains("{ <span class=\"hlrs-Ident\">x</span>; }"));
+    }
+
+    #[test]
+    fn closure_parameters_are_declared_for_the_body() {
+        let html = highlight("fn main() {\n    let f = |a: u32, mut b| a + b;\n}\n");
+        assert!(
+            html.contains(
+                "|<span class=\"hlrs-Ident hlrs-Parameter hlrs-mod-declaration\">a</span>"
+            )
+        );
+        assert!(html.contains("| <span class=\"hlrs-Ident hlrs-Parameter\">a</span>"));
+        assert!(
+            html.contains("<span class=\"hlrs-Ident hlrs-Parameter hlrs-mod-mutable\">b</span>;")
+        );
+    }
+
+    #[test]
+    fn let_else_block_is_highlighted() {
+        let html = highlight("fn main() {\n    let Some(z) = opt else { return; };\n}\n");
+        assert!(html.contains("<span class=\"hlrs-Keyword hlrs-KeywordControl\">else</span>"));
+        assert!(html.contains("<span class=\"hlrs-Keyword hlrs-KeywordControl\">return</span>"));
+    }
+
+    #[test]
+    fn try_is_a_single_element() {
+        let html = highlight("fn f() -> Option<u8> {\n    Some(g()?)\n}\n");
+        assert!(html.contains(
+            "<span class=\"hlrs-Operator hlrs-OperatorTry hlrs-KeywordControl\">?</span>"
+        ));
+    }
+
+    #[test]
+    fn struct_shorthand_reads_the_binding() {
+        let html = highlight("fn f(mut x: u8) -> Foo {\n    Foo { x }\n}\n");
+        assert!(html.contains(
+            "<span class=\"hlrs-Ident hlrs-Field hlrs-mod-mutable\"><span class=\"hlrs-Ident hlrs-Parameter\">x</span></span>"
+        ));
+    }
+
+    #[test]
+    fn wildcards_are_not_keywords() {
+        let html = highlight("fn main() {\n    match x { Some(_) => 1, _ => 2 };\n}\n");
+        assert_eq!(
+            html.matches("<span class=\"hlrs-Operator\">_</span>")
+                .count(),
+            2
+        );
+    }
+}
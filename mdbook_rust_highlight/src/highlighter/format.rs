@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use syn::{Expr, ExprLit, Ident, Lit, LitStr, Token, punctuated::Punctuated};
+
+use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+
+/// Split a `name = value` format argument into its parts.
+fn named_argument(expr: &Expr) -> Option<(&Ident, &Token![=], &Expr)> {
+    match expr {
+        Expr::Assign(assign) => match &*assign.left {
+            Expr::Path(path) if path.qself.is_none() => path
+                .path
+                .get_ident()
+                .map(|ident| (ident, &assign.eq_token, &*assign.right)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Register the arguments of a formatting macro, whose format string is
+    /// passed as the argument at `string_index`.
+    pub(crate) fn register_format_args(
+        &mut self,
+        string_index: usize,
+        token: &'ast Punctuated<Expr, Token![,]>,
+    ) {
+        let named: HashSet<String> = token
+            .iter()
+            .skip(string_index + 1)
+            .filter_map(named_argument)
+            .map(|(ident, _, _)| ident.to_string())
+            .collect();
+
+        for (index, expr) in token.iter().enumerate() {
+            if index == string_index
+                && let Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) = expr
+            {
+                self.register_lit_str(lit);
+                self.register_format_string(lit, &named);
+            } else if index > string_index
+                && let Some((ident, eq_token, value)) = named_argument(expr)
+            {
+                self.register_parameter_tag(ident);
+                self.register_operator_tag(eq_token);
+                self.register_expr(value);
+            } else {
+                self.register_expr(expr);
+            }
+        }
+    }
+
+    /// Register the `{arg:spec}` placeholders inside a format string.
+    ///
+    /// `{{` and `}}` are literal braces, and so are the braces of `\u{..}`
+    /// escapes in non raw strings.
+    pub(crate) fn register_format_string(&mut self, token: &LitStr, named: &HashSet<String>) {
+        let source = token.token().to_string();
+        let offset = token.span().byte_range().start;
+        let raw = source.starts_with('r');
+        let bytes = source.as_bytes();
+
+        let mut i = 0;
+        while i < bytes.len() {
+            match (bytes[i], bytes.get(i + 1)) {
+                (b'\\', Some(b'u')) if !raw => {
+                    i = source[i..]
+                        .find('}')
+                        .map_or(bytes.len(), |close| i + close + 1);
+                }
+                (b'\\', _) if !raw => i += 2,
+                (b'{', Some(b'{')) | (b'}', Some(b'}')) => i += 2,
+                (b'{', _) => match source[i..].find('}') {
+                    Some(close) => {
+                        self.register_placeholder(offset + i, &source[i + 1..i + close], named);
+                        i += close + 1;
+                    }
+                    None => break,
+                },
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Register a single placeholder, whose `{` is at `start`.
+    fn register_placeholder(&mut self, start: usize, inner: &str, named: &HashSet<String>) {
+        let end = start + inner.len() + 2;
+        self.register_tag_at_index(start, start + 1, TokenTag::FormatSpecifier);
+        self.register_tag_at_index(end - 1, end, TokenTag::FormatSpecifier);
+
+        let (argument, spec) = match inner.find(':') {
+            Some(colon) => (&inner[..colon], Some(colon)),
+            None => (inner, None),
+        };
+        self.register_format_argument(start + 1, argument, named);
+
+        if let Some(colon) = spec {
+            let spec_start = start + 1 + colon;
+            self.register_tag_at_index(spec_start, end - 1, TokenTag::FormatSpecifier);
+            // Width and precision may be taken from an argument, as in `{:>width$}`.
+            let spec = &inner[colon..];
+            let mut word_start = None;
+            for (i, c) in spec.char_indices() {
+                match (c, word_start) {
+                    ('$', Some(word)) => {
+                        self.register_format_argument(spec_start + word, &spec[word..i], named);
+                        word_start = None;
+                    }
+                    (c, None) if c.is_alphanumeric() || c == '_' => word_start = Some(i),
+                    (c, Some(_)) if c.is_alphanumeric() || c == '_' => {}
+                    _ => word_start = None,
+                }
+            }
+        }
+    }
+
+    /// Register a positional, named or inline captured format argument.
+    fn register_format_argument(&mut self, start: usize, name: &str, named: &HashSet<String>) {
+        if name.is_empty() {
+            return;
+        }
+        let end = start + name.len();
+        if name.bytes().all(|b| b.is_ascii_digit()) {
+            self.register_tag_at_index(start, end, TokenTag::LitNum);
+        } else if named.contains(name) {
+            self.register_tag_at_index(start, end, TokenTag::Parameter);
+        } else if let Some(binding) = self.resolve_binding(&name) {
+            self.register_tag_at_index(start, end, binding.tag);
+            for modifier in binding.modifiers.iter() {
+                self.register_modifier_at_index(start, end, modifier);
+            }
+        } else {
+            let tag = self.resolve_generic(&name).unwrap_or(TokenTag::Ident);
+            self.register_tag_at_index(start, end, tag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use syn::LitStr;
+
+    use crate::{highlighter::RustHighlighter, preprocessor::HighlightConfig, tokens::TokenTag};
+
+    /// The tags registered inside a format string literal, with their text.
+    fn format_tags<'s>(source: &'s str, named: &[&str]) -> Vec<(TokenTag, &'s str)> {
+        let lit: LitStr = syn::parse_str(source).unwrap();
+        let named = named.iter().map(|name| name.to_string()).collect();
+        let mut ident_map = HashMap::new();
+        let config = HighlightConfig::default();
+        let mut highlighter = RustHighlighter::new(&mut ident_map, &config);
+        highlighter.register_format_string(&lit, &named);
+        highlighter
+            .token_set
+            .iter()
+            .map(|token| (token.kind, &source[token.start..token.end]))
+            .collect()
+    }
+
+    #[test]
+    fn escaped_braces_are_not_placeholders() {
+        assert_eq!(
+            format_tags(r#""{{x}} {y}}}""#, &[]),
+            [
+                (TokenTag::FormatSpecifier, "{"),
+                (TokenTag::Ident, "y"),
+                (TokenTag::FormatSpecifier, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn width_is_taken_from_an_argument() {
+        assert_eq!(
+            format_tags(r#""{x:>w$}""#, &["w"]),
+            [
+                (TokenTag::FormatSpecifier, "{"),
+                (TokenTag::Ident, "x"),
+                (TokenTag::FormatSpecifier, ":>w$"),
+                (TokenTag::Parameter, "w"),
+                (TokenTag::FormatSpecifier, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_escapes_are_not_placeholders() {
+        assert_eq!(
+            format_tags(r#""\u{1F600} {0}""#, &[]),
+            [
+                (TokenTag::FormatSpecifier, "{"),
+                (TokenTag::LitNum, "0"),
+                (TokenTag::FormatSpecifier, "}"),
+            ]
+        );
+        // Raw strings have no escapes.
+        assert_eq!(
+            format_tags(r##"r"\u{x}""##, &[]),
+            [
+                (TokenTag::FormatSpecifier, "{"),
+                (TokenTag::Ident, "x"),
+                (TokenTag::FormatSpecifier, "}"),
+            ]
+        );
+    }
+}
@@ -1,23 +1,46 @@
+use mdbook_rust_highlight_derive::add_try_method;
 use syn::{
-    AngleBracketedGenericArguments, CapturedParam, GenericArgument, ParenthesizedGenericArguments,
-    PreciseCapture, TraitBound, TypeParamBound,
+    AngleBracketedGenericArguments, BoundLifetimes, CapturedParam, GenericArgument, GenericParam,
+    Generics, ParenthesizedGenericArguments, PreciseCapture, TraitBound, TraitBoundModifier,
+    TypeParamBound, WhereClause, WherePredicate,
 };
 
-use crate::highlighter::RustHighlighter;
+use crate::{
+    highlighter::RustHighlighter,
+    tokens::{Modifier, TokenTag},
+};
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_capture_param(&mut self, token: &'ast CapturedParam) {
         match token {
             CapturedParam::Ident(token) => {
-                self.register_ident_tag(token);
+                let tag = self.resolve_generic(token).unwrap_or(TokenTag::TypeParam);
+                self.register_tag(token, tag);
             }
             CapturedParam::Lifetime(token) => {
-                self.register_lifetime_tag(token);
+                self.register_lifetime(token);
             }
             _ => {}
         }
     }
-    pub(crate) fn register_trait_bound(&mut self, token: &'ast TraitBound) {}
+    pub(crate) fn register_trait_bound(&mut self, token: &'ast TraitBound) {
+        if let TraitBoundModifier::Maybe(question) = &token.modifier {
+            self.register_operator_tag(question);
+        }
+        self.try_register_bound_lifetimes(token.lifetimes.as_ref());
+        self.register_trait_path(&token.path);
+    }
+
+    /// Register a higher-ranked `for<'a>` binder.
+    #[add_try_method]
+    pub(crate) fn register_bound_lifetimes(&mut self, token: &'ast BoundLifetimes) {
+        self.register_keyword_tag(&token.for_token);
+        for param in &token.lifetimes {
+            if let GenericParam::Lifetime(param) = param {
+                self.register_lifetime_param(&param.lifetime);
+            }
+        }
+    }
 
     pub(crate) fn register_precise_capture(&mut self, token: &'ast PreciseCapture) {
         self.register_keyword_tag(&token.use_token);
@@ -29,7 +52,7 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_bound(&mut self, token: &'ast TypeParamBound) {
         match token {
             TypeParamBound::Lifetime(token) => {
-                self.register_lifetime_tag(token);
+                self.register_lifetime(token);
             }
             TypeParamBound::PreciseCapture(token) => {
                 self.register_precise_capture(token);
@@ -41,13 +64,90 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         }
     }
 
+    pub(crate) fn register_generics(&mut self, token: &'ast Generics) {
+        for param in &token.params {
+            self.register_generic_param(param);
+        }
+    }
+
+    pub(crate) fn register_generic_param(&mut self, token: &'ast GenericParam) {
+        match token {
+            GenericParam::Type(token) => {
+                self.declare_generic(&token.ident, TokenTag::TypeParam);
+                self.register_typeparam_tag(&token.ident);
+                self.register_modifier(&token.ident, Modifier::Declaration);
+                for bound in &token.bounds {
+                    self.register_bound(bound);
+                }
+                self.try_register_type(token.default.as_ref());
+            }
+            GenericParam::Lifetime(token) => {
+                self.register_lifetime_param(&token.lifetime);
+                for bound in &token.bounds {
+                    self.register_lifetime(bound);
+                }
+            }
+            GenericParam::Const(token) => {
+                self.register_keywordstorage_tag(&token.const_token);
+                self.declare_generic(&token.ident, TokenTag::ConstParam);
+                self.register_constparam_tag(&token.ident);
+                self.register_modifier(&token.ident, Modifier::Declaration);
+                self.register_type(&token.ty);
+                self.try_register_expr(token.default.as_ref());
+            }
+        }
+    }
+
+    #[add_try_method]
+    pub(crate) fn register_where_clause(&mut self, token: &'ast WhereClause) {
+        self.register_keyword_tag(&token.where_token);
+        for predicate in &token.predicates {
+            match predicate {
+                WherePredicate::Type(predicate) => {
+                    self.try_register_bound_lifetimes(predicate.lifetimes.as_ref());
+                    self.register_type(&predicate.bounded_ty);
+                    for bound in &predicate.bounds {
+                        self.register_bound(bound);
+                    }
+                }
+                WherePredicate::Lifetime(predicate) => {
+                    self.register_lifetime(&predicate.lifetime);
+                    for bound in &predicate.bounds {
+                        self.register_lifetime(bound);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub(crate) fn register_generic_argument(&mut self, token: &'ast GenericArgument) {
         match token {
             GenericArgument::Type(token) => {
                 self.register_type(token);
             }
             GenericArgument::Lifetime(token) => {
-                self.register_lifetime_tag(token);
+                self.register_lifetime(token);
+            }
+            GenericArgument::Const(token) => {
+                self.register_expr(token);
+            }
+            GenericArgument::AssocType(token) => {
+                self.register_tag(&token.ident, TokenTag::TypeAlias);
+                self.try_register_angle_brackets_arg(token.generics.as_ref());
+                self.register_type(&token.ty);
+            }
+            GenericArgument::AssocConst(token) => {
+                self.register_tag(&token.ident, TokenTag::Constant);
+                self.try_register_angle_brackets_arg(token.generics.as_ref());
+                self.register_expr(&token.value);
+            }
+            GenericArgument::Constraint(token) => {
+                self.register_tag(&token.ident, TokenTag::TypeAlias);
+                self.try_register_angle_brackets_arg(token.generics.as_ref());
+                for bound in &token.bounds {
+                    self.register_bound(bound);
+                }
             }
             _ => {}
         }
@@ -63,6 +163,7 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         self.register_return_type(&token.output);
     }
 
+    #[add_try_method]
     pub(crate) fn register_angle_brackets_arg(
         &mut self,
         token: &'ast AngleBracketedGenericArguments,
@@ -72,3 +173,27 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{highlighter::RustHighlighter, preprocessor::HighlightConfig, tokens::TokenTag};
+
+    #[test]
+    fn generic_names_are_not_remembered() {
+        let mut ident_map = HashMap::new();
+        let html = RustHighlighter::highlight(
+            "fn f<T>(it: impl Iterator<Item = T> + use<T>) {}\nconst MAX: u8 = 1;\nstatic NAME: &str = \"\";\n",
+            0,
+            &mut ident_map,
+            &HighlightConfig::default(),
+        );
+        assert!(html.contains("&lt;<span class=\"hlrs-Type hlrs-TypeParam\">T</span>&gt;)"));
+        assert!(html.contains("<span class=\"hlrs-Type hlrs-TypeAlias\">Item</span>"));
+        assert!(!ident_map.contains_key("Item"));
+        assert!(!ident_map.contains_key("T"));
+        assert_eq!(ident_map.get("MAX"), Some(&TokenTag::Constant));
+        assert_eq!(ident_map.get("NAME"), Some(&TokenTag::Static));
+    }
+}
@@ -1,9 +1,24 @@
-use syn::{FnArg, Item, ItemEnum, ItemFn, Visibility, token::Token};
+use mdbook_rust_highlight_derive::add_try_method;
+use syn::{
+    Abi, Block, Field, Fields, FnArg, ImplItem, Item, ItemConst, ItemEnum, ItemFn, ItemImpl,
+    ItemMacro, ItemStatic, ItemStruct, ItemTrait, ItemType, ItemUnion, Signature, StaticMutability,
+    TraitItem, Type, Visibility,
+};
 
-use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+use crate::{
+    highlighter::{
+        RustHighlighter,
+        scope::{Binding, BindingType},
+        unsafety::type_name,
+    },
+    tokens::{Modifier, Modifiers, TokenTag},
+};
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_item(&mut self, token: &'ast Item) {
+        // Generic parameters are only visible within the item declaring
+        // them, and nothing of an enclosing body is visible within it.
+        self.enter_item_scope();
         match token {
             Item::Fn(token) => {
                 self.register_function_item(token);
@@ -11,57 +26,325 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
             Item::Enum(token) => {
                 self.register_enum_item(token);
             }
+            Item::Struct(token) => {
+                self.register_struct_item(token);
+            }
+            Item::Union(token) => {
+                self.register_union_item(token);
+            }
+            Item::Trait(token) => {
+                self.register_trait_item(token);
+            }
+            Item::Type(token) => {
+                self.register_type_item(token);
+            }
+            Item::Impl(token) => {
+                self.register_impl_item(token);
+            }
+            Item::Const(token) => {
+                self.register_const_item(token);
+            }
+            Item::Static(token) => {
+                self.register_static_item(token);
+            }
+            Item::Macro(token) => {
+                self.register_macro_item(token);
+            }
             _ => {}
         }
+        self.exit_scope();
     }
 
     pub(crate) fn register_function_item(&mut self, token: &'ast ItemFn) {
         self.register_visibility(&token.vis);
-        self.try_register_keyword_tag(token.sig.constness.as_ref());
-        self.try_register_keyword_tag(token.sig.asyncness.as_ref());
-        self.try_register_keyword_tag(token.sig.unsafety.as_ref());
-        if let Some(abi) = &token.sig.abi {
-            self.register_keyword_tag(&abi.extern_token);
-            self.try_register_litstr_tag(abi.name.as_ref());
+        self.register_function(&token.sig, Some(&token.block));
+        self.register_item_name(&token.sig.ident, &token.attrs, Modifier::Definition);
+    }
+
+    /// Register the modifiers of a function associated to a type or a trait.
+    fn register_associated_function(&mut self, sig: &'ast Signature, of_trait: bool) {
+        if of_trait {
+            self.register_modifier(&sig.ident, Modifier::TraitMethod);
+        }
+        if sig.receiver().is_none() {
+            self.register_modifier(&sig.ident, Modifier::Static);
+        }
+    }
+
+    /// Register a function signature and its body, which shares a scope with
+    /// the parameters.
+    pub(crate) fn register_function(&mut self, sig: &'ast Signature, block: Option<&'ast Block>) {
+        self.try_register_keywordstorage_tag(sig.constness.as_ref());
+        self.try_register_keyword_tag(sig.asyncness.as_ref());
+        self.try_register_keyword_tag(sig.unsafety.as_ref());
+        self.try_register_abi(sig.abi.as_ref());
+        self.register_keyworddeclaration_tag(&sig.fn_token);
+        self.register_function_tag(&sig.ident);
+        if sig.asyncness.is_some() {
+            self.register_modifier(&sig.ident, Modifier::Async);
+        }
+        if sig.unsafety.is_some() {
+            self.register_modifier(&sig.ident, Modifier::Unsafe);
         }
-        self.register_keyword_tag(&token.sig.fn_token);
-        self.register_function_tag(&token.sig.ident);
 
-        for input in &token.sig.inputs {
+        self.enter_scope(TokenTag::Parameter);
+        self.register_generics(&sig.generics);
+        for input in &sig.inputs {
             match input {
                 FnArg::Receiver(arg) => {
-                    self.register_selftoken_tag(&arg.self_token);
-                    self.try_register_keyword_tag(arg.mutability.as_ref());
-                    self.try_register_lifetime_tag(arg.lifetime());
+                    let mut modifiers = Modifiers::default();
+                    if arg.mutability.is_some() {
+                        modifiers.insert(Modifier::Mutable);
+                    }
+                    if let Type::Reference(_) = &*arg.ty {
+                        modifiers.insert(Modifier::Reference);
+                    } else {
+                        self.register_modifier(&arg.self_token, Modifier::Consuming);
+                    }
+                    let binding = self.declare_binding_as(
+                        &"self",
+                        Binding {
+                            tag: TokenTag::SelfToken,
+                            modifiers,
+                            ty: BindingType::Unknown,
+                        },
+                    );
+                    self.register_binding(&arg.self_token, binding);
+                    self.register_modifier(&arg.self_token, Modifier::Declaration);
+                    self.try_register_keywordstorage_tag(arg.mutability.as_ref());
+                    self.try_register_lifetime(arg.lifetime());
                 }
                 FnArg::Typed(type_pat) => {
                     self.register_type_pattern(type_pat);
                     self.register_type(&type_pat.ty);
+                    self.annotate_binding(&type_pat.pat, Some(&type_pat.ty), None);
                 }
             }
         }
 
-        self.register_return_type(&token.sig.output);
-        self.register_block(&token.block);
+        self.register_return_type(&sig.output);
+        self.try_register_where_clause(sig.generics.where_clause.as_ref());
+        self.try_register_block(block);
+        self.exit_scope();
     }
 
     pub(crate) fn register_enum_item(&mut self, token: &'ast ItemEnum) {
         self.register_visibility(&token.vis);
-        self.register_keyword_tag(&token.enum_token);
-        self.register_tag(&token.ident, TokenTag::Type);
-        // TODO REGISTER GENERICS AND FIELDS
+        self.register_keyworddeclaration_tag(&token.enum_token);
+        self.register_enum_tag(&token.ident);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        self.register_generics(&token.generics);
+        self.try_register_where_clause(token.generics.where_clause.as_ref());
         for variant in &token.variants {
-            self.register_enum_tag(&variant.ident);
+            self.register_variant_tag(&variant.ident);
+            self.register_item_name(&variant.ident, &variant.attrs, Modifier::Definition);
+            self.register_fields(&variant.fields);
             if let Some((_, discriminant)) = &variant.discriminant {
                 self.register_expr(discriminant);
             }
         }
     }
 
+    pub(crate) fn register_struct_item(&mut self, token: &'ast ItemStruct) {
+        self.register_visibility(&token.vis);
+        self.register_keyworddeclaration_tag(&token.struct_token);
+        self.register_struct_tag(&token.ident);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        self.register_generics(&token.generics);
+        self.try_register_where_clause(token.generics.where_clause.as_ref());
+        self.register_fields(&token.fields);
+    }
+
+    pub(crate) fn register_union_item(&mut self, token: &'ast ItemUnion) {
+        self.register_visibility(&token.vis);
+        self.register_keyworddeclaration_tag(&token.union_token);
+        self.register_union_tag(&token.ident);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        self.register_generics(&token.generics);
+        self.try_register_where_clause(token.generics.where_clause.as_ref());
+        for field in &token.fields.named {
+            self.register_field(field);
+        }
+    }
+
+    pub(crate) fn register_fields(&mut self, token: &'ast Fields) {
+        for field in token {
+            self.register_field(field);
+        }
+    }
+
+    pub(crate) fn register_field(&mut self, token: &'ast Field) {
+        self.register_visibility(&token.vis);
+        if let Some(ident) = &token.ident {
+            self.register_field_tag(ident);
+            self.register_item_name(ident, &token.attrs, Modifier::Definition);
+        }
+        self.register_type(&token.ty);
+    }
+
+    pub(crate) fn register_trait_item(&mut self, token: &'ast ItemTrait) {
+        self.register_visibility(&token.vis);
+        self.try_register_keyword_tag(token.unsafety.as_ref());
+        self.try_register_keyword_tag(token.auto_token.as_ref());
+        self.register_keyworddeclaration_tag(&token.trait_token);
+        self.register_trait_tag(&token.ident);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        if token.unsafety.is_some() {
+            self.register_modifier(&token.ident, Modifier::Unsafe);
+        }
+        self.register_generics(&token.generics);
+        for bound in &token.supertraits {
+            self.register_bound(bound);
+        }
+        self.try_register_where_clause(token.generics.where_clause.as_ref());
+        let enclosing = self.self_type.replace(token.ident.to_string());
+        for item in &token.items {
+            match item {
+                TraitItem::Fn(item) => {
+                    self.register_function(&item.sig, item.default.as_ref());
+                    let modifier = Self::definition_modifier(item.default.is_some());
+                    self.register_item_name(&item.sig.ident, &item.attrs, modifier);
+                    self.register_associated_function(&item.sig, true);
+                }
+                TraitItem::Type(item) => {
+                    self.register_keyworddeclaration_tag(&item.type_token);
+                    self.register_typealias_tag(&item.ident);
+                    let modifier = Self::definition_modifier(item.default.is_some());
+                    self.register_item_name(&item.ident, &item.attrs, modifier);
+                    self.register_generics(&item.generics);
+                    for bound in &item.bounds {
+                        self.register_bound(bound);
+                    }
+                    if let Some((_, ty)) = &item.default {
+                        self.register_type(ty);
+                    }
+                }
+                TraitItem::Macro(item) => {
+                    self.register_macro(&item.mac);
+                }
+                TraitItem::Const(item) => {
+                    self.register_keywordstorage_tag(&item.const_token);
+                    self.register_tag(&item.ident, TokenTag::Constant);
+                    let modifier = Self::definition_modifier(item.default.is_some());
+                    self.register_item_name(&item.ident, &item.attrs, modifier);
+                    self.register_type(&item.ty);
+                    if let Some((_, expr)) = &item.default {
+                        self.register_expr(expr);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.self_type = enclosing;
+    }
+
+    pub(crate) fn register_type_item(&mut self, token: &'ast ItemType) {
+        self.register_visibility(&token.vis);
+        self.register_keyworddeclaration_tag(&token.type_token);
+        self.register_typealias_tag(&token.ident);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        self.register_generics(&token.generics);
+        self.register_type(&token.ty);
+    }
+
+    pub(crate) fn register_impl_item(&mut self, token: &'ast ItemImpl) {
+        self.try_register_keyword_tag(token.defaultness.as_ref());
+        self.try_register_keyword_tag(token.unsafety.as_ref());
+        self.register_keyworddeclaration_tag(&token.impl_token);
+        self.register_generics(&token.generics);
+        if let Some((bang, path, for_token)) = &token.trait_ {
+            self.try_register_operator_tag(bang.as_ref());
+            self.register_trait_path(path);
+            self.register_keyword_tag(for_token);
+        }
+        self.register_type(&token.self_ty);
+        self.try_register_where_clause(token.generics.where_clause.as_ref());
+        let enclosing = std::mem::replace(&mut self.self_type, type_name(&token.self_ty));
+        for item in &token.items {
+            match item {
+                ImplItem::Fn(item) => {
+                    self.register_visibility(&item.vis);
+                    self.try_register_keyword_tag(item.defaultness.as_ref());
+                    self.register_function(&item.sig, Some(&item.block));
+                    self.register_item_name(&item.sig.ident, &item.attrs, Modifier::Definition);
+                    self.register_associated_function(&item.sig, token.trait_.is_some());
+                }
+                ImplItem::Type(item) => {
+                    self.register_visibility(&item.vis);
+                    self.register_keyworddeclaration_tag(&item.type_token);
+                    self.register_typealias_tag(&item.ident);
+                    self.register_item_name(&item.ident, &item.attrs, Modifier::Definition);
+                    self.register_generics(&item.generics);
+                    self.register_type(&item.ty);
+                }
+                ImplItem::Macro(item) => {
+                    self.register_macro(&item.mac);
+                }
+                ImplItem::Const(item) => {
+                    self.register_visibility(&item.vis);
+                    self.register_keywordstorage_tag(&item.const_token);
+                    self.register_tag(&item.ident, TokenTag::Constant);
+                    self.register_item_name(&item.ident, &item.attrs, Modifier::Definition);
+                    self.register_type(&item.ty);
+                    self.register_expr(&item.expr);
+                }
+                _ => {}
+            }
+        }
+        self.self_type = enclosing;
+    }
+
+    pub(crate) fn register_macro_item(&mut self, token: &'ast ItemMacro) {
+        self.register_macro(&token.mac);
+        // The name defined by `macro_rules! name { .. }`.
+        if let Some(ident) = &token.ident {
+            self.register_macro_tag(ident);
+            self.register_item_name(ident, &token.attrs, Modifier::Definition);
+        }
+    }
+
+    pub(crate) fn register_const_item(&mut self, token: &'ast ItemConst) {
+        self.register_visibility(&token.vis);
+        self.register_keywordstorage_tag(&token.const_token);
+        self.register_tag(&token.ident, TokenTag::Constant);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        self.register_type(&token.ty);
+        self.register_expr(&token.expr);
+    }
+
+    pub(crate) fn register_static_item(&mut self, token: &'ast ItemStatic) {
+        self.register_visibility(&token.vis);
+        self.register_keywordstorage_tag(&token.static_token);
+        self.register_tag(&token.ident, TokenTag::Static);
+        self.register_item_name(&token.ident, &token.attrs, Modifier::Definition);
+        self.register_modifier(&token.ident, Modifier::Static);
+        if let StaticMutability::Mut(mutability) = &token.mutability {
+            self.register_keywordstorage_tag(mutability);
+            self.register_modifier(&token.ident, Modifier::Mutable);
+        }
+        self.register_type(&token.ty);
+        self.register_expr(&token.expr);
+    }
+
+    /// The modifier of a trait item, which is only declared unless it has a
+    /// default.
+    fn definition_modifier(has_default: bool) -> Modifier {
+        match has_default {
+            true => Modifier::Definition,
+            false => Modifier::Declaration,
+        }
+    }
+
+    #[add_try_method]
+    pub(crate) fn register_abi(&mut self, token: &'ast Abi) {
+        self.register_keyworddeclaration_tag(&token.extern_token);
+        self.try_register_litstr_tag(token.name.as_ref());
+    }
+
     pub(crate) fn register_visibility(&mut self, token: &'ast Visibility) {
         match token {
             Visibility::Inherited => {}
-            _ => self.register_keyword_tag(token),
+            _ => self.register_keywordvisibility_tag(token),
         }
     }
 }
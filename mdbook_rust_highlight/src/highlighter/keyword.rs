@@ -0,0 +1,54 @@
+use proc_macro2::Ident;
+
+use crate::{
+    builtins::{CONTROL_FLOW_KEYWORDS, DECLARATION_KEYWORDS, STORAGE_KEYWORDS},
+    highlighter::RustHighlighter,
+    tokens::TokenTag,
+};
+
+/// The refined tag of a keyword, `Keyword` itself for those of no particular kind.
+fn keyword_tag(keyword: &str) -> TokenTag {
+    if keyword == "pub" {
+        TokenTag::KeywordVisibility
+    } else if CONTROL_FLOW_KEYWORDS.contains(&keyword) {
+        TokenTag::KeywordControl
+    } else if STORAGE_KEYWORDS.contains(&keyword) {
+        TokenTag::KeywordStorage
+    } else if DECLARATION_KEYWORDS.contains(&keyword) {
+        TokenTag::KeywordDeclaration
+    } else {
+        TokenTag::Keyword
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Register a keyword of a token stream, refined by its text alone, as
+    /// there is no syntax to tell what it does.
+    ///
+    /// Keywords of the syntax tree are refined where they are registered.
+    pub(crate) fn register_lexical_keyword(&mut self, token: &Ident) {
+        self.register_tag(token, keyword_tag(&token.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn for_is_control_flow_in_loops_only() {
+        let html = highlight(
+            "impl Trait for X where F: for<'a> Fn(&'a u8) {\n    fn f() {\n        for i in 0..1 {}\n    }\n}\n",
+        );
+        assert_eq!(
+            html.matches("<span class=\"hlrs-Keyword\">for</span>")
+                .count(),
+            2
+        );
+        assert_eq!(
+            html.matches("<span class=\"hlrs-Keyword hlrs-KeywordControl\">for</span>")
+                .count(),
+            1
+        );
+    }
+}
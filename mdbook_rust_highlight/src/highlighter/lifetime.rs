@@ -0,0 +1,76 @@
+use mdbook_rust_highlight_derive::add_try_method;
+use syn::{Label, Lifetime};
+
+use crate::{
+    highlighter::RustHighlighter,
+    tokens::{Modifier, TokenTag},
+};
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Register the declaration of a lifetime parameter, to which its uses
+    /// in the declaring item are linked.
+    pub(crate) fn register_lifetime_param(&mut self, token: &Lifetime) {
+        let start = token.span().byte_range().start;
+        self.declare_lifetime(&token.ident, start);
+        self.register_lifetime(token);
+        self.register_modifier(token, Modifier::Declaration);
+    }
+
+    #[add_try_method]
+    pub(crate) fn register_lifetime(&mut self, token: &Lifetime) {
+        let range = token.span().byte_range();
+        self.register_lifetime_at(range.start, range.end, &token.ident.to_string());
+    }
+
+    /// Register a lifetime spanning `start..end`, where `name` is the
+    /// identifier following its `'`.
+    pub(crate) fn register_lifetime_at(&mut self, start: usize, end: usize, name: &str) {
+        let tag = match name {
+            "static" => TokenTag::LifeTimeStatic,
+            "_" => TokenTag::LifeTimeElided,
+            _ => TokenTag::LifeTime,
+        };
+        self.register_tag_at_index(start, end, tag);
+        if let Some(declaration) = self.resolve_lifetime(&name) {
+            self.links.insert(start, declaration);
+        }
+    }
+
+    /// Register the label of a loop or a block, to which `break` and
+    /// `continue` inside it are linked.
+    #[add_try_method]
+    pub(crate) fn register_label(&mut self, token: &Label) {
+        let start = token.name.span().byte_range().start;
+        self.declare_label(&token.name.ident, start);
+        self.register_label_use(&token.name);
+        self.register_modifier(&token.name, Modifier::Declaration);
+    }
+
+    #[add_try_method]
+    pub(crate) fn register_label_use(&mut self, token: &Lifetime) {
+        self.register_tag(token, TokenTag::Label);
+        if let Some(declaration) = self.resolve_label(&token.ident) {
+            self.links
+                .insert(token.span().byte_range().start, declaration);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn uses_point_to_the_id_of_their_declaration() {
+        let html = highlight("fn f<'a>(x: &'a str) -> &'a str {\n    x\n}\n");
+        assert_eq!(html.matches("id=\"hlrs-0-5\"").count(), 1);
+        assert_eq!(html.matches("href=\"#hlrs-0-5\">'a</a>").count(), 2);
+    }
+
+    #[test]
+    fn labels_point_to_their_loop() {
+        let html = highlight("fn f() {\n    'outer: loop {\n        break 'outer;\n    }\n}\n");
+        assert_eq!(html.matches("id=\"hlrs-0-13\"").count(), 1);
+        assert_eq!(html.matches("href=\"#hlrs-0-13\">'outer</a>").count(), 1);
+    }
+}
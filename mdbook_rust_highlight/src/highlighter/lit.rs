@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use proc_macro2::Literal;
+use syn::{Lit, LitStr, spanned::Spanned};
+
+use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+
+/// The ranges of the escape sequences in the source of a non raw literal,
+/// such as `\n`, `\x7f` and `\u{1F600}`.
+///
+/// Only the `\` of a line continuation is an escape, the whitespace it skips
+/// is left as is.
+fn escape_ranges(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut escapes = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            i += 1;
+            continue;
+        }
+        let len = match bytes.get(i + 1) {
+            Some(b'x') => 4,
+            Some(b'u') => source[i..].find('}').map_or(2, |close| close + 1),
+            Some(b'\n' | b'\r') => 1,
+            _ => 2,
+        };
+        let end = (i + len).min(bytes.len());
+        escapes.push(i..end);
+        i = end;
+    }
+    escapes
+}
+
+/// Whether the source of a string literal is raw, as in `r#"..."#`, `br"..."`
+/// and `cr"..."`.
+fn is_raw(source: &str) -> bool {
+    source
+        .split(['"', '\''])
+        .next()
+        .is_some_and(|prefix| prefix.contains('r'))
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    pub(crate) fn register_lit(&mut self, token: &Lit) {
+        match token {
+            Lit::Bool(lit) => self.register_litbool_tag(lit),
+            Lit::Int(lit) => self.register_lit_number(lit, lit.suffix()),
+            Lit::Float(lit) => self.register_lit_number(lit, lit.suffix()),
+            Lit::Str(lit) => self.register_lit_str(lit),
+            Lit::ByteStr(lit) => self.register_lit_text(&lit.token(), TokenTag::LitByte),
+            Lit::CStr(lit) => self.register_lit_text(&lit.token(), TokenTag::LitStr),
+            Lit::Byte(lit) => self.register_lit_text(&lit.token(), TokenTag::LitByte),
+            Lit::Char(lit) => self.register_lit_text(&lit.token(), TokenTag::LitChar),
+            Lit::Verbatim(lit) => self.register_lit_verbatim(lit),
+            _ => {}
+        }
+    }
+
+    /// Register a string literal along with its escape sequences.
+    pub(crate) fn register_lit_str(&mut self, token: &LitStr) {
+        self.register_lit_text(&token.token(), TokenTag::LitStr);
+    }
+
+    /// Register a number, whose type suffix, as in `10u8`, is tagged apart.
+    fn register_lit_number(&mut self, token: &impl Spanned, suffix: &str) {
+        self.register_litnum_tag(token);
+        if !suffix.is_empty() {
+            let end = token.span().byte_range().end;
+            self.register_tag_at_index(end - suffix.len(), end, TokenTag::LitSuffix);
+        }
+    }
+
+    /// Register a quoted literal, tagged as raw when it is, and otherwise
+    /// with its escape sequences.
+    fn register_lit_text(&mut self, token: &Literal, tag: TokenTag) {
+        let source = token.to_string();
+        if is_raw(&source) {
+            self.register_tag(token, TokenTag::LitRawStr);
+            return;
+        }
+        self.register_tag(token, tag);
+        let offset = token.span().byte_range().start;
+        for escape in escape_ranges(&source) {
+            self.register_tag_at_index(
+                offset + escape.start,
+                offset + escape.end,
+                TokenTag::LitEscape,
+            );
+        }
+    }
+
+    /// Register a literal syn can't classify by the way it starts.
+    fn register_lit_verbatim(&mut self, token: &Literal) {
+        let source = token.to_string();
+        match source.as_bytes() {
+            [b'0'..=b'9', ..] => self.register_litnum_tag(token),
+            [b'\'', ..] => self.register_lit_text(token, TokenTag::LitChar),
+            [b'b', ..] => self.register_lit_text(token, TokenTag::LitByte),
+            _ => self.register_lit_text(token, TokenTag::LitStr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_ranges, is_raw};
+
+    fn escapes(source: &str) -> Vec<&str> {
+        escape_ranges(source)
+            .into_iter()
+            .map(|range| &source[range])
+            .collect()
+    }
+
+    #[test]
+    fn escapes_span_their_whole_sequence() {
+        assert_eq!(
+            escapes(r#""a\n\t\\\"\0""#),
+            [r"\n", r"\t", r"\\", r#"\""#, r"\0"]
+        );
+        assert_eq!(escapes(r#"b"\x7f\x00""#), [r"\x7f", r"\x00"]);
+        assert_eq!(escapes(r#""\u{1F600}\u{e9}""#), [r"\u{1F600}", r"\u{e9}"]);
+        assert_eq!(escapes(r"'\''"), [r"\'"]);
+    }
+
+    #[test]
+    fn line_continuations_keep_their_whitespace() {
+        assert_eq!(escapes("\"a\\\n    b\""), ["\\"]);
+        assert_eq!(escapes("\"a\\\r\n    b\""), ["\\"]);
+    }
+
+    #[test]
+    fn truncated_escapes_stop_at_the_end() {
+        assert_eq!(escapes(r"\x7"), [r"\x7"]);
+        assert_eq!(escapes(r"\u{1F"), [r"\u"]);
+    }
+
+    #[test]
+    fn raw_literals_are_recognized_by_their_prefix() {
+        assert!(is_raw(r##"r#"\n"#"##));
+        assert!(is_raw(r#"br"\n""#));
+        assert!(is_raw(r#"cr"\n""#));
+        assert!(!is_raw(r#"b"\n""#));
+        assert!(!is_raw(r"'r'"));
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use syn::{
+    Expr, Ident, Macro, Pat, Token, parse::ParseStream, punctuated::Punctuated, spanned::Spanned,
+    visit::Visit,
+};
+
+use crate::{
+    builtins::{
+        STD_ASM_MACROS, STD_EXPR_MACROS, STD_FORMAT_MACROS, STD_MATCHES_MACROS, STD_VEC_MACROS,
+    },
+    highlighter::{RustHighlighter, asm::AsmArg},
+    tokens::{Modifier, TokenTag},
+};
+
+/// Whether a macro is one of the builtin macros of the standard library.
+fn is_std_macro(ident: &Ident) -> bool {
+    [
+        STD_EXPR_MACROS,
+        STD_VEC_MACROS,
+        STD_MATCHES_MACROS,
+        STD_ASM_MACROS,
+    ]
+    .iter()
+    .any(|names| names.iter().any(|name| ident == name))
+        || STD_FORMAT_MACROS.iter().any(|(name, _)| ident == name)
+}
+
+/// The arguments of a builtin macro, parsed according to its known grammar.
+pub(crate) enum MacroArgs {
+    /// `assert!(a)`, `dbg!(a, b)` and the like.
+    Exprs(Punctuated<Expr, Token![,]>),
+    /// `println!("{}", x)`, `write!(f, "{x}")` and other macros taking a
+    /// format string at a known position.
+    Format {
+        string_index: usize,
+        exprs: Punctuated<Expr, Token![,]>,
+    },
+    /// `vec![elem; len]`
+    Repeat { elem: Box<Expr>, len: Box<Expr> },
+    /// `matches!(expr, pat if guard)`
+    Matches {
+        expr: Box<Expr>,
+        pat: Box<Pat>,
+        guard: Option<(Token![if], Box<Expr>)>,
+    },
+    /// `asm!("mov {0}, 5", out(reg) x)` and the other inline assembly macros.
+    Asm(Punctuated<AsmArg, Token![,]>),
+}
+
+impl MacroArgs {
+    fn parse_exprs(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self::Exprs(Punctuated::parse_terminated(input)?))
+    }
+
+    fn parse_vec(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Self::Exprs(Punctuated::new()));
+        }
+        let fork = input.fork();
+        fork.parse::<Expr>()?;
+        if !fork.peek(Token![;]) {
+            return Self::parse_exprs(input);
+        }
+        let elem = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let len = input.parse()?;
+        Ok(Self::Repeat { elem, len })
+    }
+
+    fn parse_matches(input: ParseStream) -> syn::Result<Self> {
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let pat = Box::new(Pat::parse_multi_with_leading_vert(input)?);
+        let guard = match input.peek(Token![if]) {
+            true => Some((input.parse()?, input.parse()?)),
+            false => None,
+        };
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self::Matches { expr, pat, guard })
+    }
+}
+
+/// Parse the arguments of a builtin macro whose grammar is known.
+fn parse_builtin_macro(token: &Macro) -> Option<MacroArgs> {
+    let name = token.path.segments.last()?.ident.to_string();
+    let format_string = STD_FORMAT_MACROS
+        .iter()
+        .find_map(|(macro_name, index)| (*macro_name == name).then_some(*index));
+    let args = if let Some(string_index) = format_string {
+        token
+            .parse_body_with(Punctuated::parse_terminated)
+            .map(|exprs| MacroArgs::Format {
+                string_index,
+                exprs,
+            })
+    } else if STD_EXPR_MACROS.contains(&name.as_str()) {
+        token.parse_body_with(MacroArgs::parse_exprs)
+    } else if STD_VEC_MACROS.contains(&name.as_str()) {
+        token.parse_body_with(MacroArgs::parse_vec)
+    } else if STD_MATCHES_MACROS.contains(&name.as_str()) {
+        token.parse_body_with(MacroArgs::parse_matches)
+    } else if STD_ASM_MACROS.contains(&name.as_str()) {
+        token
+            .parse_body_with(Punctuated::parse_terminated)
+            .map(MacroArgs::Asm)
+    } else {
+        return None;
+    };
+    args.ok()
+}
+
+/// The arguments of every builtin macro of a file, by the start of the macro.
+///
+/// The arguments are not part of the syntax tree, so they are parsed before
+/// it is highlighted, to live as long as it does.
+#[derive(Default)]
+pub(crate) struct BuiltinMacros {
+    pub(crate) args: HashMap<usize, MacroArgs>,
+}
+
+impl BuiltinMacros {
+    /// Visit the arguments of a builtin macro, which may call builtin macros
+    /// as well, as in `println!("{:?}", vec![1])`.
+    fn visit_macro_args(&mut self, args: &MacroArgs) {
+        match args {
+            MacroArgs::Exprs(exprs) | MacroArgs::Format { exprs, .. } => {
+                for expr in exprs {
+                    self.visit_expr(expr);
+                }
+            }
+            MacroArgs::Repeat { elem, len } => {
+                self.visit_expr(elem);
+                self.visit_expr(len);
+            }
+            MacroArgs::Matches { expr, pat, guard } => {
+                self.visit_expr(expr);
+                self.visit_pat(pat);
+                if let Some((_, guard)) = guard {
+                    self.visit_expr(guard);
+                }
+            }
+            MacroArgs::Asm(args) => {
+                for arg in args {
+                    match arg {
+                        AsmArg::Operand { expr, output, .. } => {
+                            self.visit_expr(expr);
+                            if let Some((_, output)) = output {
+                                self.visit_expr(output);
+                            }
+                        }
+                        AsmArg::Expr(expr) => self.visit_expr(expr),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for BuiltinMacros {
+    fn visit_macro(&mut self, i: &'ast Macro) {
+        if let Some(args) = parse_builtin_macro(i) {
+            self.visit_macro_args(&args);
+            self.args.insert(i.span().byte_range().start, args);
+        }
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    pub(crate) fn register_macro(&mut self, token: &'ast Macro) {
+        self.register_macro_tag(&token.path);
+        self.register_macro_tag(&token.bang_token);
+        if token
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "asm")
+        {
+            self.register_modifier(&token.path, Modifier::Unsafe);
+            self.register_modifier(&token.bang_token, Modifier::Unsafe);
+        }
+        if token
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| is_std_macro(&segment.ident))
+        {
+            self.register_modifier(&token.path, Modifier::Library);
+            self.register_modifier(&token.bang_token, Modifier::Library);
+        }
+        match self
+            .macro_args
+            .get(&token.span().byte_range().start)
+            .copied()
+        {
+            Some(args) => self.register_macro_args(args),
+            None => self.register_token_stream(token.tokens.clone()),
+        }
+    }
+
+    pub(crate) fn register_macro_args(&mut self, token: &'ast MacroArgs) {
+        match token {
+            MacroArgs::Exprs(exprs) => {
+                for expr in exprs {
+                    self.register_expr(expr);
+                }
+            }
+            MacroArgs::Format {
+                string_index,
+                exprs,
+            } => {
+                self.register_format_args(*string_index, exprs);
+            }
+            MacroArgs::Repeat { elem, len } => {
+                self.register_expr(elem);
+                self.register_expr(len);
+            }
+            MacroArgs::Matches { expr, pat, guard } => {
+                self.register_expr(expr);
+                self.enter_scope(TokenTag::Local);
+                self.register_pat(pat);
+                if let Some((if_token, guard)) = guard {
+                    self.register_keywordcontrol_tag(if_token);
+                    self.register_expr(guard);
+                }
+                self.exit_scope();
+            }
+            MacroArgs::Asm(args) => self.register_asm_args(args),
+        }
+    }
+}
@@ -1,68 +1,201 @@
 use crate::{
-    highlighter::error::IdentificationError,
-    preprocessor::IdentMap,
-    tokens::{SpannedToken, TokenTag},
+    highlighter::{
+        attribute::Attributes,
+        definitions::Definitions,
+        error::IdentificationError,
+        macros::{BuiltinMacros, MacroArgs},
+        scope::Scope,
+        span_tree::{Edge, SpanTree, insert_token},
+        unsafety::UnsafeItems,
+    },
+    preprocessor::{HighlightConfig, IdentMap},
+    tokens::{Modifiers, SpannedToken, TokenTag},
 };
-use regex::Regex;
-use ropey::Rope;
-use std::collections::{BTreeSet, HashMap};
-use syn::{File, Ident, PathSegment, spanned::Spanned, visit::Visit};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use syn::{File, PathSegment, spanned::Spanned, visit::Visit};
 
+pub mod asm;
+pub mod attribute;
+pub mod boring;
+pub mod comment;
+pub mod definitions;
+pub mod doc;
 pub mod error;
 pub mod expr;
+pub mod format;
 pub mod generics;
 pub mod item;
+pub mod keyword;
+pub mod lifetime;
+pub mod lit;
+pub mod macros;
+pub mod modifier;
 pub mod pat;
 pub mod path;
+pub mod scope;
+pub mod span_tree;
 pub mod statement;
+pub mod token_tree;
 pub mod ty;
+pub mod unsafety;
 pub mod visit;
 
+/// Escape text written in the page, so that nothing in the code, such as
+/// `Vec<&str>` or a `"</span>"` string, is read as markup.
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 pub struct RustHighlighter<'a, 'ast> {
+    /// Every registered token, nested into a [`SpanTree`] once written.
     token_set: BTreeSet<SpannedToken>,
     unidentified: HashMap<usize, &'ast PathSegment>,
+    /// The arguments of every builtin macro, by the start of the macro.
+    macro_args: HashMap<usize, &'ast MacroArgs>,
+    /// The number of lines of every hidden region, by its start.
+    boring_regions: HashMap<usize, usize>,
+    /// The declaration of every linked lifetime and label, by its start.
+    links: HashMap<usize, usize>,
+    /// The index of the highlighted block in its chapter, which makes the
+    /// ids of its declarations unique in the page.
+    block: usize,
+    /// The documentation of every intra-doc link to the library, by its start.
+    doc_links: HashMap<usize, String>,
+    /// The modifiers of every span, until they are attached to its tag.
+    modifiers: HashMap<(usize, usize), Modifiers>,
+    /// Items of the snippet marked `#[deprecated]`.
+    deprecated: HashSet<String>,
     ident_map: IdentMap<'a>,
+    config: &'a HighlightConfig,
+    scopes: Vec<Scope>,
+    /// The last segment of the type of the enclosing impl, or the name of
+    /// the enclosing trait, which `Self` stands for.
+    self_type: Option<String>,
+    unsafe_items: UnsafeItems,
 }
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
-    pub(crate) fn highlight(code: &str, ident_map: IdentMap<'a>) -> String {
-        let mut highlighter = Self::new(ident_map);
+    /// Highlight the code of the `block`th code block of a chapter.
+    pub(crate) fn highlight(
+        code: &str,
+        block: usize,
+        ident_map: IdentMap<'a>,
+        config: &'a HighlightConfig,
+    ) -> String {
+        let mut highlighter = Self::new(ident_map, config);
+        highlighter.block = block;
 
         let code = highlighter.register_boring(code);
 
-        let mut output = Rope::from_str(&code);
-
         let syntax_tree: File =
-            syn::parse_str(&code).expect(&format!("Failed to parse Rust code\n{}", code));
-
-        highlighter.visit_file(&syntax_tree);
+            syn::parse_str(&code).unwrap_or_else(|_| panic!("Failed to parse Rust code\n{}", code));
+        let mut macros = BuiltinMacros::default();
+        macros.visit_file(&syntax_tree);
+        highlighter.register_file(&syntax_tree, &macros);
         highlighter.register_comments(&code);
-        highlighter.write_tokens(&mut output);
+        highlighter.attach_modifiers();
+        highlighter.write_tokens(&code)
+    }
 
-        output.to_string()
+    /// Register every token of a parsed file, comments aside.
+    pub(crate) fn register_file(&mut self, syntax_tree: &'ast File, macros: &'ast BuiltinMacros) {
+        self.macro_args
+            .extend(macros.args.iter().map(|(start, args)| (*start, args)));
+        let mut definitions = Definitions::default();
+        definitions.visit_file(syntax_tree);
+        for (name, tag) in definitions.items {
+            self.remember_ident(&name, tag);
+        }
+        self.deprecated.extend(definitions.deprecated);
+        self.unsafe_items.visit_file(syntax_tree);
+        self.visit_file(syntax_tree);
+        let mut attributes = Attributes::default();
+        attributes.visit_file(syntax_tree);
+        for attribute in attributes.attributes {
+            self.register_attribute(attribute);
+        }
     }
 
-    pub(crate) fn write_tokens(&mut self, output: &mut Rope) {
-        let mut tok_offset: usize = 0;
-        let mut set_iterator = self.token_set.iter();
-        while let Some(token) = set_iterator.next() {
-            let identified = match self.identify_token(&token) {
-                Ok(identified) => identified,
-                Err(IdentificationError::AlreadyIdentified) => token.clone(),
-                Err(IdentificationError::NoIdentificationNeeded) => {
-                    let _ = set_iterator.next();
-                    continue;
-                }
-            };
-            let tag = identified.kind.to_string();
-            output.insert(identified.start + tok_offset, tag.as_str());
-            tok_offset += tag.len();
+    /// Write the code with the elements of its tokens, its text escaped.
+    pub(crate) fn write_tokens(&mut self, code: &str) -> String {
+        let tokens = std::mem::take(&mut self.token_set);
+        let tree = SpanTree::build(
+            tokens
+                .into_iter()
+                .filter_map(|token| self.identified(token)),
+        );
+        let mut output = String::with_capacity(code.len());
+        let mut written = 0;
+        for (offset, edge) in tree.edges() {
+            output.push_str(&escape_html(&code[written..offset]));
+            written = offset;
+            match edge {
+                Edge::Open(token) => output.push_str(&self.opening_tag(token)),
+                Edge::Close(token) => output.push_str(self.closing_tag(token)),
+            }
         }
-        self.token_set.clear();
+        output.push_str(&escape_html(&code[written..]));
         self.unidentified.clear();
+        self.macro_args.clear();
+        self.boring_regions.clear();
+        self.links.clear();
+        self.doc_links.clear();
+        self.modifiers.clear();
+        output
     }
 
-    pub(crate) fn remember_ident(&mut self, ident: &(impl Spanned + ToString), token: TokenTag) {
+    fn opening_tag(&self, token: &SpannedToken) -> String {
+        match token.kind {
+            TokenTag::Boring => self.boring_region_opening(token.start),
+            // A declaration is linked to itself, and its uses are anchors
+            // to its id.
+            TokenTag::LifeTime | TokenTag::Label => match self.links.get(&token.start) {
+                Some(&declaration) if declaration == token.start => format!(
+                    "<span class=\"{}\" id=\"{}\">",
+                    token.classes(),
+                    self.declaration_id(declaration)
+                ),
+                Some(&declaration) => format!(
+                    "<a class=\"{}\" href=\"#{}\">",
+                    token.classes(),
+                    self.declaration_id(declaration)
+                ),
+                None => format!("<span class=\"{}\">", token.classes()),
+            },
+            TokenTag::DocLink if self.doc_links.contains_key(&token.start) => {
+                format!(
+                    "<a class=\"{}\" href=\"{}\">",
+                    token.classes(),
+                    self.doc_links[&token.start]
+                )
+            }
+            _ => format!("<span class=\"{}\">", token.classes()),
+        }
+    }
+
+    /// The id of the element of a declaration starting at `start`.
+    fn declaration_id(&self, start: usize) -> String {
+        format!("hlrs-{}-{start}", self.block)
+    }
+
+    fn closing_tag(&self, token: &SpannedToken) -> &'static str {
+        match token.kind {
+            TokenTag::LifeTime | TokenTag::Label
+                if self
+                    .links
+                    .get(&token.start)
+                    .is_some_and(|declaration| *declaration != token.start) =>
+            {
+                "</a>"
+            }
+            TokenTag::DocLink if self.doc_links.contains_key(&token.start) => "</a>",
+            _ => token.kind.closing(),
+        }
+    }
+
+    pub(crate) fn remember_ident(&mut self, ident: &impl ToString, token: TokenTag) {
         self.ident_map.insert(ident.to_string().leak(), token);
     }
 
@@ -87,14 +220,23 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
 
                 Ok(SpannedToken {
                     kind: identified,
-                    start: token.start,
-                    end: token.end,
+                    ..*token
                 })
             }
             _ => Err(IdentificationError::AlreadyIdentified),
         }
     }
 
+    /// The token as it is written, or `None` for a segment whose
+    /// identification was dropped, as it was registered again since.
+    pub(crate) fn identified(&self, token: SpannedToken) -> Option<SpannedToken> {
+        match self.identify_token(&token) {
+            Ok(identified) => Some(identified),
+            Err(IdentificationError::AlreadyIdentified) => Some(token),
+            Err(IdentificationError::NoIdentificationNeeded) => None,
+        }
+    }
+
     /// Extract a span position in the rope.
     ///
     /// returns the (start_idx, end_idx) of the span
@@ -105,16 +247,18 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     }
 
     pub(crate) fn register_tag_at_index(&mut self, start: usize, end: usize, tag: TokenTag) {
-        self.token_set.insert(SpannedToken {
+        self.register_token(SpannedToken {
             kind: tag,
             start,
             end,
+            modifiers: Modifiers::default(),
         });
-        self.token_set.insert(SpannedToken {
-            kind: TokenTag::EndOfToken,
-            start: end,
-            end: usize::MAX,
-        });
+    }
+
+    /// Register a token, merged with any registration of the same tag over
+    /// the same range.
+    pub(crate) fn register_token(&mut self, token: SpannedToken) {
+        insert_token(&mut self.token_set, token);
     }
 
     pub(crate) fn register_tag(&mut self, token: &impl Spanned, tag: TokenTag) {
@@ -126,44 +270,32 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         self.remember_ident(ident, tag);
         self.register_tag(ident, tag);
     }
+}
 
-    pub(crate) fn register_comments(&mut self, code: &str) {
-        let comment_regex: Regex = Regex::new(r"\/\/\/?[^\n]*").unwrap();
-        for comment in comment_regex.captures_iter(code) {
-            let m = comment.get(0).unwrap();
-            self.register_tag_at_index(m.start(), m.end(), TokenTag::Comment);
-        }
-    }
-
-    pub(crate) fn register_boring(&mut self, code: &str) -> String {
-        // FIX BUG THAT IT WILL NOT WORK ON THE END OR START, AND ADD A WAY TO PROCESS MULTIPLE TOKEN
-        // MAYBE THE ORDERED SET INSTEAD OF MAP WILL SOLVE THIS.
-        // #(\s*)([^\[\n][^\n]*)
-        // let boring_regex = Regex::new(r"(?m)(#\s)(.*)$").unwrap();
-        let mut string_offset = 0;
-        let mut output = String::with_capacity(code.len());
-        for line in code.split_inclusive('\n') {
-            if let Some(hash_position) = line.find("# ") {
-                let after_hash = &line[(hash_position + 2)..];
-                let start = string_offset + hash_position;
-                let end = string_offset + line.len() - 2;
-                output.push_str(after_hash);
-                self.register_tag_at_index(start, end, TokenTag::Boring);
-            } else {
-                output.push_str(line);
-            }
-            string_offset += line.len() - 2;
-        }
-        output
-    }
+/// Highlight code as the first block of a chapter, with the default
+/// configuration.
+#[cfg(test)]
+pub(crate) fn highlight(code: &str) -> String {
+    RustHighlighter::highlight(code, 0, &mut HashMap::new(), &HighlightConfig::default())
 }
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
-    fn new(ident_map: IdentMap<'a>) -> Self {
+    fn new(ident_map: IdentMap<'a>, config: &'a HighlightConfig) -> Self {
         Self {
             token_set: BTreeSet::new(),
             unidentified: HashMap::new(),
+            macro_args: HashMap::new(),
+            boring_regions: HashMap::new(),
+            links: HashMap::new(),
+            block: 0,
+            doc_links: HashMap::new(),
+            modifiers: HashMap::new(),
+            deprecated: HashSet::new(),
             ident_map,
+            config,
+            scopes: Vec::new(),
+            self_type: None,
+            unsafe_items: UnsafeItems::default(),
         }
     }
 }
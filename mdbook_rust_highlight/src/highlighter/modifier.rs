@@ -0,0 +1,87 @@
+use syn::{Attribute, Ident, spanned::Spanned};
+
+use crate::{
+    builtins::{STD_ENUMS, STD_STRUCTS, STD_TRAITS, STD_VARIANTS},
+    highlighter::RustHighlighter,
+    tokens::{Modifier, SpannedToken, TokenTag},
+};
+
+/// Whether an item is marked `#[deprecated]`.
+pub(crate) fn is_deprecated(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("deprecated"))
+}
+
+/// Whether a name is one of the traits, types or variants of the standard
+/// library.
+pub(crate) fn is_library_name(name: &str) -> bool {
+    [STD_TRAITS, STD_STRUCTS, STD_ENUMS, STD_VARIANTS]
+        .iter()
+        .any(|names| names.contains(&name))
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    pub(crate) fn register_modifier(&mut self, token: &impl Spanned, modifier: Modifier) {
+        let (start, end) = Self::span_position(&token.span());
+        self.register_modifier_at_index(start, end, modifier);
+    }
+
+    /// Register a modifier of the outermost tag spanning `start..end`,
+    /// whether that tag is registered before or after it.
+    pub(crate) fn register_modifier_at_index(
+        &mut self,
+        start: usize,
+        end: usize,
+        modifier: Modifier,
+    ) {
+        self.modifiers
+            .entry((start, end))
+            .or_default()
+            .insert(modifier);
+    }
+
+    /// Register the name of an item where it is defined, or only declared
+    /// when it has no body, along with the modifiers its attributes imply.
+    pub(crate) fn register_item_name(
+        &mut self,
+        ident: &Ident,
+        attrs: &[Attribute],
+        modifier: Modifier,
+    ) {
+        self.register_modifier(ident, modifier);
+        if is_deprecated(attrs) {
+            self.register_modifier(ident, Modifier::Deprecated);
+        }
+    }
+
+    /// Register the modifiers a name carries wherever it is used.
+    pub(crate) fn register_name_modifiers(&mut self, ident: &Ident) {
+        let name = ident.to_string();
+        if is_library_name(&name) {
+            self.register_modifier(ident, Modifier::Library);
+        }
+        if self.deprecated.contains(&name) {
+            self.register_modifier(ident, Modifier::Deprecated);
+        }
+    }
+
+    /// Move the registered modifiers onto the outermost tag of their span.
+    pub(crate) fn attach_modifiers(&mut self) {
+        if self.modifiers.is_empty() {
+            return;
+        }
+        self.token_set = std::mem::take(&mut self.token_set)
+            .into_iter()
+            .map(|token| match token.kind {
+                TokenTag::Boring => token,
+                _ => match self.modifiers.remove(&(token.start, token.end)) {
+                    Some(modifiers) => SpannedToken {
+                        modifiers: token.modifiers.union(modifiers),
+                        ..token
+                    },
+                    None => token,
+                },
+            })
+            .collect();
+        self.modifiers.clear();
+    }
+}
@@ -1,6 +1,12 @@
-use syn::{Pat, PatIdent, PatOr, PatReference, PatTuple, PatTupleStruct, PatType};
+use syn::{
+    Member, Pat, PatIdent, PatOr, PatReference, PatSlice, PatStruct, PatTuple, PatTupleStruct,
+    PatType,
+};
 
-use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+use crate::{
+    highlighter::RustHighlighter,
+    tokens::{Modifier, Modifiers, TokenTag},
+};
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_pat(&mut self, token: &'ast Pat) {
@@ -29,18 +35,64 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
             Pat::Lit(token) => {
                 self.register_lit_expr(token);
             }
+            Pat::Struct(token) => {
+                self.register_struct_pat(token);
+            }
+            Pat::Slice(token) => {
+                self.register_slice_pat(token);
+            }
+            Pat::Range(token) => {
+                self.register_range_expr(token);
+            }
+            Pat::Wild(token) => {
+                self.register_operator_tag(&token.underscore_token);
+            }
+            Pat::Rest(token) => {
+                self.register_operator_tag(&token.dot2_token);
+            }
+            Pat::Paren(token) => {
+                self.register_pat(&token.pat);
+            }
+            Pat::Const(token) => {
+                self.register_const_expr(token);
+            }
+            Pat::Macro(token) => {
+                self.register_macro(&token.mac);
+            }
             _ => {}
         }
     }
 
     pub(crate) fn register_ident_pat(&mut self, token: &'ast PatIdent) {
-        self.try_register_keyword_tag(token.by_ref.as_ref());
-        self.try_register_keyword_tag(token.mutability.as_ref());
-        self.register_ident_tag(&token.ident);
+        // A lone variant name matches the variant rather than binding a new name.
+        if token.by_ref.is_none()
+            && token.mutability.is_none()
+            && token.subpat.is_none()
+            && let Some(TokenTag::Variant) = self.ident_map.get(token.ident.to_string().as_str())
+        {
+            self.register_variant_tag(&token.ident);
+            return;
+        }
+        self.try_register_keywordstorage_tag(token.by_ref.as_ref());
+        self.try_register_keywordstorage_tag(token.mutability.as_ref());
+        let mut modifiers = Modifiers::default();
+        if token.mutability.is_some() {
+            modifiers.insert(Modifier::Mutable);
+        }
+        if token.by_ref.is_some() {
+            modifiers.insert(Modifier::Reference);
+        }
+        let binding = self.declare_binding(&token.ident, modifiers);
+        self.register_binding(&token.ident, binding);
+        self.register_modifier(&token.ident, Modifier::Declaration);
+        if let Some((at, subpat)) = &token.subpat {
+            self.register_operator_tag(at);
+            self.register_pat(subpat);
+        }
     }
 
     pub(crate) fn register_reference_pat(&mut self, token: &'ast PatReference) {
-        self.try_register_keyword_tag(token.mutability.as_ref());
+        self.try_register_keywordstorage_tag(token.mutability.as_ref());
         self.register_pat(&token.pat);
     }
 
@@ -61,12 +113,33 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
 
     pub(crate) fn register_tuple_struct_pat(&mut self, token: &'ast PatTupleStruct) {
         self.try_register_qself(token.qself.as_ref());
-        self.register_path(&token.path, Some(TokenTag::Enum));
+        self.register_constructor_path(&token.path);
         for arg in &token.elems {
             self.register_pat(arg);
         }
     }
 
+    pub(crate) fn register_struct_pat(&mut self, token: &'ast PatStruct) {
+        self.try_register_qself(token.qself.as_ref());
+        self.register_constructor_path(&token.path);
+        for field in &token.fields {
+            // In the `Foo { x }` shorthand the member and the binding share a span.
+            if field.colon_token.is_some() || matches!(field.member, Member::Unnamed(_)) {
+                self.register_member(&field.member);
+            }
+            self.register_pat(&field.pat);
+        }
+        if let Some(rest) = &token.rest {
+            self.register_operator_tag(&rest.dot2_token);
+        }
+    }
+
+    pub(crate) fn register_slice_pat(&mut self, token: &'ast PatSlice) {
+        for elem in &token.elems {
+            self.register_pat(elem);
+        }
+    }
+
     pub(crate) fn register_or_pat(&mut self, token: &'ast PatOr) {
         for case in &token.cases {
             self.register_pat(case);
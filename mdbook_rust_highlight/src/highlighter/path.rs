@@ -1,7 +1,11 @@
 use mdbook_rust_highlight_derive::add_try_method;
-use syn::{Path, PathArguments, PathSegment, QSelf, spanned::Spanned};
+use syn::{Ident, Path, PathArguments, PathSegment, QSelf, spanned::Spanned};
 
-use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+use crate::{builtins::PRIMITIVE_TYPES, highlighter::RustHighlighter, tokens::TokenTag};
+
+pub(crate) fn is_primitive(ident: &Ident) -> bool {
+    PRIMITIVE_TYPES.iter().any(|primitive| ident == primitive)
+}
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_path_argument(&mut self, token: &'ast PathArguments) {
@@ -22,13 +26,66 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         tag: Option<TokenTag>,
     ) {
         self.register_path_argument(&token.arguments);
+        if let Some(generic) = self.resolve_generic(&token.ident) {
+            self.register_tag(&token.ident, generic);
+            return;
+        }
         match tag {
             None => {
                 self.register_tag(&token.ident, TokenTag::NeedIdentification);
                 self.unidentified
-                    .insert(token.span().byte_range().start, &token);
+                    .insert(token.span().byte_range().start, token);
             }
-            Some(tag) => self.register_ident(&token.ident, tag),
+            Some(TokenTag::Type) => self.register_type_segment(&token.ident),
+            Some(tag) => self.register_tag(&token.ident, tag),
+        }
+    }
+
+    /// Register a segment in type position, refining `Type` by what is
+    /// already known about the name.
+    pub(crate) fn register_type_segment(&mut self, token: &'ast Ident) {
+        if is_primitive(token) {
+            self.register_primitive_tag(token);
+            return;
+        }
+        match self.known_type(token) {
+            Some(tag) => self.register_tag(token, tag),
+            None => self.register_type_tag(token),
+        }
+    }
+
+    /// The type tag remembered for the name, if any.
+    pub(crate) fn known_type(&self, token: &Ident) -> Option<TokenTag> {
+        self.ident_map
+            .get(token.to_string().as_str())
+            .copied()
+            .filter(TokenTag::is_type)
+    }
+
+    /// Register the path of a struct expression or pattern, whose last
+    /// segment names either a struct or an enum variant.
+    ///
+    /// An unknown name is only guessed from the shape of the path, so it is
+    /// not remembered for later code blocks.
+    pub(crate) fn register_constructor_path(&mut self, token: &'ast Path) {
+        let last_tag = match token.segments.last() {
+            Some(segment) => match self.ident_map.get(segment.ident.to_string().as_str()) {
+                Some(tag) if tag.is_type() || *tag == TokenTag::Variant => *tag,
+                // A single segment names a struct, a longer path usually an enum variant.
+                _ if token.segments.len() == 1 => TokenTag::Type,
+                _ => TokenTag::Variant,
+            },
+            None => return,
+        };
+        self.register_path(token, Some(last_tag));
+    }
+
+    /// Register the path of a trait bound or an implemented trait, whose
+    /// last segment is remembered as a trait wherever it is defined.
+    pub(crate) fn register_trait_path(&mut self, token: &'ast Path) {
+        self.register_path(token, Some(TokenTag::Trait));
+        if let Some(segment) = token.segments.last() {
+            self.remember_ident(&segment.ident, TokenTag::Trait);
         }
     }
 
@@ -50,10 +107,62 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         let mut segment_iter = token.segments.iter().rev();
         let last_segment = segment_iter.next();
         for segment in segment_iter {
-            self.register_segment_tag(segment);
+            if let Some(generic) = self.resolve_generic(&segment.ident) {
+                self.register_tag(&segment.ident, generic);
+            } else if is_primitive(&segment.ident) {
+                self.register_primitive_tag(&segment.ident);
+            } else if let Some(tag) = self.known_type(&segment.ident) {
+                self.register_tag(&segment.ident, tag);
+            } else {
+                self.register_segment_tag(&segment.ident);
+            }
+            self.register_path_argument(&segment.arguments);
+            self.register_name_modifiers(&segment.ident);
         }
         if let Some(seg) = last_segment {
             self.register_path_segment(seg, last_tag);
+            self.register_name_modifiers(&seg.ident);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{highlighter::RustHighlighter, preprocessor::HighlightConfig, tokens::TokenTag};
+
+    #[test]
+    fn guessed_constructors_are_not_remembered() {
+        let mut ident_map = HashMap::new();
+        let config = HighlightConfig::default();
+        let html = RustHighlighter::highlight(
+            "fn main() {\n    let s = config::Settings { verbose: true };\n}\n",
+            0,
+            &mut ident_map,
+            &config,
+        );
+        assert!(html.contains("<span class=\"hlrs-Variant\">Settings</span>"));
+        assert!(!ident_map.contains_key("Settings"));
+
+        let html = RustHighlighter::highlight(
+            "fn main() {\n    let Settings = 1;\n}\n",
+            0,
+            &mut ident_map,
+            &config,
+        );
+        assert!(html.contains("hlrs-Local hlrs-mod-declaration\">Settings</span>"));
+    }
+
+    #[test]
+    fn bounds_are_remembered_as_traits() {
+        let mut ident_map = HashMap::new();
+        RustHighlighter::highlight(
+            "fn show<T: Render>(value: T) {}\n",
+            0,
+            &mut ident_map,
+            &HighlightConfig::default(),
+        );
+        assert_eq!(ident_map.get("Render"), Some(&TokenTag::Trait));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use syn::spanned::Spanned;
+
+use crate::{
+    highlighter::RustHighlighter,
+    tokens::{Modifiers, TokenTag},
+};
+
+/// What is known about the value held by a binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum BindingType {
+    #[default]
+    Unknown,
+    RawPointer,
+    Union,
+}
+
+/// A name bound by a pattern, a parameter or a receiver.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Binding {
+    pub(crate) tag: TokenTag,
+    /// Modifiers of the binding wherever it is named, such as `mutable`.
+    pub(crate) modifiers: Modifiers,
+    pub(crate) ty: BindingType,
+}
+
+/// The bindings introduced by a single lexical scope.
+pub(crate) struct Scope {
+    /// Tag given to the bindings declared directly in this scope.
+    binding_tag: TokenTag,
+    /// Whether this is the scope of an item, past which no name is visible.
+    item: bool,
+    bindings: HashMap<String, Binding>,
+    /// Generic type and const parameters, which live in their own namespace.
+    generics: HashMap<String, TokenTag>,
+    /// Lifetime parameters, by the offset of their declaration.
+    lifetimes: HashMap<String, usize>,
+    /// Labels of loops and blocks, by the offset of their declaration.
+    labels: HashMap<String, usize>,
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    pub(crate) fn enter_scope(&mut self, binding_tag: TokenTag) {
+        self.push_scope(binding_tag, false);
+    }
+
+    /// Enter the scope of an item, which can't see the bindings, generics,
+    /// lifetimes and labels of the body it may be nested in.
+    pub(crate) fn enter_item_scope(&mut self) {
+        self.push_scope(TokenTag::Local, true);
+    }
+
+    fn push_scope(&mut self, binding_tag: TokenTag, item: bool) {
+        self.scopes.push(Scope {
+            binding_tag,
+            item,
+            bindings: HashMap::new(),
+            generics: HashMap::new(),
+            lifetimes: HashMap::new(),
+            labels: HashMap::new(),
+        });
+    }
+
+    pub(crate) fn exit_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// The scopes a name may be resolved in, from the innermost one out to
+    /// the scope of the enclosing item.
+    fn visible_scopes(&self) -> impl Iterator<Item = &Scope> {
+        let item = self.scopes.iter().rposition(|scope| scope.item);
+        self.scopes[item.unwrap_or(0)..].iter().rev()
+    }
+
+    /// Declare a binding in the innermost scope, tagged by the kind of that scope.
+    ///
+    /// The tag is `Local` when there is no enclosing scope.
+    pub(crate) fn declare_binding(
+        &mut self,
+        ident: &impl ToString,
+        modifiers: Modifiers,
+    ) -> Binding {
+        let tag = self
+            .scopes
+            .last()
+            .map_or(TokenTag::Local, |scope| scope.binding_tag);
+        self.declare_binding_as(
+            ident,
+            Binding {
+                tag,
+                modifiers,
+                ty: BindingType::Unknown,
+            },
+        )
+    }
+
+    /// Declare a binding in the innermost scope with an explicit tag.
+    pub(crate) fn declare_binding_as(
+        &mut self,
+        ident: &impl ToString,
+        binding: Binding,
+    ) -> Binding {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.bindings.insert(ident.to_string(), binding);
+        }
+        binding
+    }
+
+    /// Look a name up from the innermost scope outwards.
+    pub(crate) fn resolve_binding(&self, ident: &impl ToString) -> Option<Binding> {
+        let name = ident.to_string();
+        self.visible_scopes()
+            .find_map(|scope| scope.bindings.get(&name).copied())
+    }
+
+    pub(crate) fn binding_mut(&mut self, ident: &impl ToString) -> Option<&mut Binding> {
+        let name = ident.to_string();
+        let item = self.scopes.iter().rposition(|scope| scope.item);
+        self.scopes[item.unwrap_or(0)..]
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.get_mut(&name))
+    }
+
+    /// Declare a generic parameter in the innermost scope.
+    ///
+    /// Unlike item names these are never remembered in the shared
+    /// [`IdentMap`](crate::preprocessor::IdentMap), as they mean nothing
+    /// outside the item declaring them.
+    pub(crate) fn declare_generic(&mut self, ident: &impl ToString, tag: TokenTag) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.generics.insert(ident.to_string(), tag);
+        }
+    }
+
+    /// Look a generic parameter up from the innermost scope outwards.
+    pub(crate) fn resolve_generic(&self, ident: &impl ToString) -> Option<TokenTag> {
+        let name = ident.to_string();
+        self.visible_scopes()
+            .find_map(|scope| scope.generics.get(&name).copied())
+    }
+
+    /// Declare a lifetime parameter, declared at `offset`, in the innermost scope.
+    pub(crate) fn declare_lifetime(&mut self, name: &impl ToString, offset: usize) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.lifetimes.insert(name.to_string(), offset);
+        }
+    }
+
+    /// Look a lifetime parameter up from the innermost scope outwards.
+    pub(crate) fn resolve_lifetime(&self, name: &impl ToString) -> Option<usize> {
+        let name = name.to_string();
+        self.visible_scopes()
+            .find_map(|scope| scope.lifetimes.get(&name).copied())
+    }
+
+    /// Declare a label, declared at `offset`, in the innermost scope.
+    pub(crate) fn declare_label(&mut self, name: &impl ToString, offset: usize) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.labels.insert(name.to_string(), offset);
+        }
+    }
+
+    /// Look a label up from the innermost scope outwards.
+    pub(crate) fn resolve_label(&self, name: &impl ToString) -> Option<usize> {
+        let name = name.to_string();
+        self.visible_scopes()
+            .find_map(|scope| scope.labels.get(&name).copied())
+    }
+
+    /// Register a declaration or a use of a binding.
+    pub(crate) fn register_binding(&mut self, token: &impl Spanned, binding: Binding) {
+        self.register_tag(token, binding.tag);
+        for modifier in binding.modifiers.iter() {
+            self.register_modifier(token, modifier);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn nested_items_do_not_see_the_enclosing_body() {
+        let html = highlight(
+            "fn outer(a: u8) {\n    let b = a;\n    fn inner() {\n        a;\n        b;\n    }\n}\n",
+        );
+        assert!(html.contains("        <span class=\"hlrs-Ident\">a</span>;"));
+        assert!(html.contains("        <span class=\"hlrs-Ident\">b</span>;"));
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::BTreeSet;
+
+use crate::tokens::{SpannedToken, TokenTag};
+
+/// Insert a token, merging it with the one already registered with the same
+/// tag over the same range.
+pub(crate) fn insert_token(tokens: &mut BTreeSet<SpannedToken>, token: SpannedToken) {
+    let token = match tokens.take(&token) {
+        Some(registered) => SpannedToken {
+            modifiers: registered.modifiers.union(token.modifiers),
+            ..token
+        },
+        None => token,
+    };
+    tokens.insert(token);
+}
+
+/// A token along with the tokens nested in it, in order.
+struct SpanNode {
+    token: SpannedToken,
+    children: Vec<SpanNode>,
+}
+
+impl SpanNode {
+    fn push_edges<'t>(&'t self, edges: &mut Vec<(usize, Edge<'t>)>) {
+        edges.push((self.token.start, Edge::Open(&self.token)));
+        for child in &self.children {
+            child.push_edges(edges);
+        }
+        edges.push((self.token.end, Edge::Close(&self.token)));
+    }
+}
+
+/// Where the element of a token opens or closes.
+pub(crate) enum Edge<'t> {
+    Open(&'t SpannedToken),
+    Close(&'t SpannedToken),
+}
+
+/// Tokens nested so that each one closes before the one enclosing it, which
+/// makes the HTML written from them well formed, as long as the text between
+/// their elements is escaped.
+#[derive(Default)]
+pub(crate) struct SpanTree {
+    roots: Vec<SpanNode>,
+}
+
+impl SpanTree {
+    /// Nest the tokens, splitting the ones that overlap.
+    ///
+    /// Hidden regions are never split, so the tokens crossing their edges
+    /// are split there first. Then a token crossing the end of the one
+    /// enclosing it is split there, and the rest of it nests wherever it
+    /// falls. Empty tokens are dropped.
+    pub(crate) fn build(tokens: impl IntoIterator<Item = SpannedToken>) -> Self {
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .filter(|token| token.start < token.end)
+            .collect();
+        let mut edges: Vec<usize> = tokens
+            .iter()
+            .filter(|token| token.kind == TokenTag::Boring)
+            .flat_map(|region| [region.start, region.end])
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        let mut queue = BTreeSet::new();
+        for token in tokens {
+            let mut start = token.start;
+            if token.kind != TokenTag::Boring {
+                for &edge in edges
+                    .iter()
+                    .filter(|edge| token.start < **edge && **edge < token.end)
+                {
+                    insert_token(
+                        &mut queue,
+                        SpannedToken {
+                            start,
+                            end: edge,
+                            ..token.clone()
+                        },
+                    );
+                    start = edge;
+                }
+            }
+            insert_token(&mut queue, SpannedToken { start, ..token });
+        }
+
+        let mut tree = Self::default();
+        // The nodes enclosing the next token, the innermost last.
+        let mut open = Vec::new();
+        while let Some(mut token) = queue.pop_first() {
+            tree.close_before(&mut open, token.start);
+            if let Some(node) = open.last()
+                && node.token.end < token.end
+            {
+                insert_token(
+                    &mut queue,
+                    SpannedToken {
+                        start: node.token.end,
+                        ..token.clone()
+                    },
+                );
+                token.end = node.token.end;
+            }
+            open.push(SpanNode {
+                token,
+                children: Vec::new(),
+            });
+        }
+        tree.close_before(&mut open, usize::MAX);
+        tree
+    }
+
+    /// Close the open nodes ending at or before `offset`.
+    ///
+    /// A piece of a split token may have the same tag over the same range
+    /// as a token enclosing it, into which it is merged.
+    fn close_before(&mut self, open: &mut Vec<SpanNode>, offset: usize) {
+        while open.last().is_some_and(|node| node.token.end <= offset) {
+            let node = open.pop().unwrap();
+            let same = open
+                .iter_mut()
+                .rev()
+                .take_while(|open| {
+                    open.token.start == node.token.start && open.token.end == node.token.end
+                })
+                .find(|open| open.token == node.token);
+            let nodes = match same {
+                Some(same) => {
+                    same.token.modifiers = same.token.modifiers.union(node.token.modifiers);
+                    node.children
+                }
+                None => vec![node],
+            };
+            match open.last_mut() {
+                Some(parent) => parent.children.extend(nodes),
+                None => self.roots.extend(nodes),
+            }
+        }
+    }
+
+    /// The edges of every element, by their offset in the highlighted code,
+    /// in the order they are written.
+    pub(crate) fn edges(&self) -> Vec<(usize, Edge<'_>)> {
+        let mut edges = Vec::new();
+        for root in &self.roots {
+            root.push_edges(&mut edges);
+        }
+        edges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Edge, SpanTree};
+    use crate::tokens::{Modifier, Modifiers, SpannedToken, TokenTag};
+
+    const TAGS: &[TokenTag] = &[
+        TokenTag::Keyword,
+        TokenTag::Ident,
+        TokenTag::LitStr,
+        TokenTag::LitEscape,
+        TokenTag::Comment,
+        TokenTag::Operator,
+    ];
+
+    /// A xorshift generator, so that failures are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    fn token(kind: TokenTag, start: usize, end: usize) -> SpannedToken {
+        SpannedToken {
+            kind,
+            start,
+            end,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Random tokens over `len` bytes, many of them overlapping or sharing a
+    /// range, and a few hidden regions, which never overlap one another.
+    fn random_tokens(rng: &mut Rng, len: usize) -> Vec<SpannedToken> {
+        let mut tokens = Vec::new();
+        for _ in 0..rng.below(30) {
+            let start = rng.below(len);
+            let end = start + rng.below(len - start + 1);
+            let mut token = token(TAGS[rng.below(TAGS.len())], start, end);
+            if rng.below(3) == 0 {
+                token.modifiers.insert(Modifier::Mutable);
+            }
+            if rng.below(4) == 0 {
+                tokens.push(token.clone());
+            }
+            tokens.push(token);
+        }
+        let mut region_start = rng.below(len);
+        while region_start < len {
+            let region_end = region_start + 1 + rng.below(len - region_start);
+            if rng.below(2) == 0 {
+                tokens.push(token(TokenTag::Boring, region_start, region_end));
+            }
+            region_start = region_end + rng.below(len);
+        }
+        tokens
+    }
+
+    /// The opening and closing of every element, in order, as they would be
+    /// written.
+    fn written(tree: &SpanTree) -> Vec<(usize, bool, TokenTag, usize, usize)> {
+        tree.edges()
+            .into_iter()
+            .map(|(offset, edge)| match edge {
+                Edge::Open(token) => (offset, true, token.kind, token.start, token.end),
+                Edge::Close(token) => (offset, false, token.kind, token.start, token.end),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn random_overlapping_tokens_nest() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let len = 1 + rng.below(40);
+            let tokens = random_tokens(&mut rng, len);
+            let tree = SpanTree::build(tokens.clone());
+            let edges = written(&tree);
+
+            // Every element closes inside the one it was opened in, and
+            // edges are written in order.
+            let mut open = Vec::new();
+            let mut previous = 0;
+            for &(offset, opens, kind, start, end) in &edges {
+                assert!(previous <= offset, "edges out of order in {edges:?}");
+                previous = offset;
+                match opens {
+                    true => {
+                        assert!(start < end, "empty element in {edges:?}");
+                        // Regions are never nested in a token.
+                        assert!(kind != TokenTag::Boring || open.is_empty());
+                        open.push((kind, start, end));
+                    }
+                    false => assert_eq!(open.pop(), Some((kind, start, end))),
+                }
+            }
+            assert!(open.is_empty());
+
+            let elements: Vec<_> = edges
+                .iter()
+                .filter(|edge| edge.1)
+                .map(|&(_, _, kind, start, end)| (kind, start, end))
+                .collect();
+            // Registrations of the same tag over the same range are merged.
+            let mut unique = elements.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), elements.len(), "duplicates in {edges:?}");
+
+            for token in tokens.iter().filter(|token| token.start < token.end) {
+                if token.kind == TokenTag::Boring {
+                    assert!(elements.contains(&(token.kind, token.start, token.end)));
+                    continue;
+                }
+                // The pieces of a split token cover all of it.
+                for offset in token.start..token.end {
+                    assert!(
+                        elements.iter().any(|&(kind, start, end)| kind == token.kind
+                            && start <= offset
+                            && offset < end),
+                        "{token:?} lost {offset} in {edges:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn building_is_deterministic() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            let mut tokens = random_tokens(&mut rng, 30);
+            let tree = SpanTree::build(tokens.clone());
+            for i in (1..tokens.len()).rev() {
+                tokens.swap(i, rng.below(i + 1));
+            }
+            assert_eq!(written(&tree), written(&SpanTree::build(tokens)));
+        }
+    }
+
+    #[test]
+    fn duplicates_merge_their_modifiers() {
+        let mut mutable = token(TokenTag::Ident, 0, 3);
+        mutable.modifiers.insert(Modifier::Mutable);
+        let mut declaration = token(TokenTag::Ident, 0, 3);
+        declaration.modifiers.insert(Modifier::Declaration);
+        let tree = SpanTree::build([mutable, declaration]);
+        let edges = tree.edges();
+        let [(0, Edge::Open(token)), (3, Edge::Close(_))] = edges.as_slice() else {
+            panic!("not a single element");
+        };
+        assert!(token.modifiers.contains(Modifier::Mutable));
+        assert!(token.modifiers.contains(Modifier::Declaration));
+    }
+
+    #[test]
+    fn crossing_tokens_are_split() {
+        let tree = SpanTree::build([
+            token(TokenTag::LitStr, 0, 6),
+            token(TokenTag::Comment, 4, 10),
+        ]);
+        let pieces: HashMap<_, _> = written(&tree)
+            .into_iter()
+            .filter(|edge| edge.1)
+            .map(|(_, _, kind, start, end)| ((start, end), kind))
+            .collect();
+        assert_eq!(pieces[&(0, 6)], TokenTag::LitStr);
+        assert_eq!(pieces[&(4, 6)], TokenTag::Comment);
+        assert_eq!(pieces[&(6, 10)], TokenTag::Comment);
+    }
+
+    #[test]
+    fn regions_split_the_tokens_they_cross() {
+        let tree = SpanTree::build([
+            token(TokenTag::LitStr, 0, 6),
+            token(TokenTag::Boring, 4, 10),
+        ]);
+        assert_eq!(
+            written(&tree),
+            [
+                (0, true, TokenTag::LitStr, 0, 4),
+                (4, false, TokenTag::LitStr, 0, 4),
+                (4, true, TokenTag::Boring, 4, 10),
+                (4, true, TokenTag::LitStr, 4, 6),
+                (6, false, TokenTag::LitStr, 4, 6),
+                (10, false, TokenTag::Boring, 4, 10),
+            ]
+        );
+    }
+}
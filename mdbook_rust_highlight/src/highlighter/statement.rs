@@ -1,8 +1,7 @@
 use mdbook_rust_highlight_derive::add_try_method;
-use proc_macro2::TokenTree;
-use syn::{Block, LitStr, Local, LocalInit, Stmt, StmtMacro};
+use syn::{Block, Local, LocalInit, Stmt, StmtMacro};
 
-use crate::highlighter::RustHighlighter;
+use crate::{highlighter::RustHighlighter, tokens::TokenTag};
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     #[add_try_method]
@@ -24,32 +23,34 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     }
 
     pub(crate) fn register_macro_statement(&mut self, token: &'ast StmtMacro) {
-        // TODO NEED CHANGE TO RENDER PATH CORRECTLY AND TO PARSE TOKEN TREE BETTER WITH SPECIFIC KEY WORD FOR BUILTIN MACROS
-        self.register_macro_tag(&token.mac.path);
-        self.register_macro_tag(&token.mac.bang_token);
-        for token in token.mac.tokens.clone() {
-            if let TokenTree::Literal(lit) = token {
-                if let Ok(_) = syn::parse_str::<LitStr>(&lit.to_string()) {
-                    self.register_litstr_tag(&lit);
-                }
-            }
-        }
+        self.register_macro(&token.mac);
     }
 
+    #[add_try_method]
     pub(crate) fn register_block(&mut self, token: &'ast Block) {
+        self.enter_scope(TokenTag::Local);
         for statement in &token.stmts {
-            self.register_statement(&statement);
+            self.register_statement(statement);
         }
+        self.exit_scope();
     }
 
     pub(crate) fn register_local(&mut self, token: &'ast Local) {
-        self.register_keyword_tag(&token.let_token);
-        self.register_pat(&token.pat);
+        self.register_keywordstorage_tag(&token.let_token);
+        // The initializer can't see the bindings it is assigned to.
         self.try_register_local_init(token.init.as_ref());
+        self.register_pat(&token.pat);
+        let init = token.init.as_ref().map(|init| &*init.expr);
+        self.annotate_binding(&token.pat, None, init);
     }
 
     #[add_try_method]
     pub(crate) fn register_local_init(&mut self, token: &'ast LocalInit) {
         self.register_expr(&token.expr);
+        // The `else` of a `let ... else` can't see the bindings either.
+        if let Some((else_token, diverge)) = &token.diverge {
+            self.register_keywordcontrol_tag(else_token);
+            self.register_expr(diverge);
+        }
     }
 }
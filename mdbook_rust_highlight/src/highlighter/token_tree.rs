@@ -0,0 +1,86 @@
+use proc_macro2::{Delimiter, Ident, Literal, Spacing, TokenStream, TokenTree};
+use syn::Lit;
+
+use crate::{
+    builtins::{KEYWORDS, PRIMITIVE_TYPES},
+    highlighter::RustHighlighter,
+    tokens::TokenTag,
+};
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Lexically classify every token of a stream whose grammar is unknown,
+    /// such as the body of a macro call or the arguments of an attribute.
+    pub(crate) fn register_token_stream(&mut self, tokens: TokenStream) {
+        let mut iter = tokens.into_iter().peekable();
+        while let Some(token) = iter.next() {
+            match token {
+                TokenTree::Group(group) => {
+                    self.register_token_stream(group.stream());
+                }
+                TokenTree::Literal(literal) => {
+                    self.register_literal_token(literal);
+                }
+                TokenTree::Punct(punct) => {
+                    // Lifetimes and `macro_rules!` metavariables are a punctuation
+                    // followed by an identifier.
+                    let lifetime = match punct.as_char() {
+                        '\'' if punct.spacing() == Spacing::Joint => true,
+                        '$' => false,
+                        _ => continue,
+                    };
+                    if let Some(TokenTree::Ident(ident)) = iter.peek() {
+                        let start = punct.span().byte_range().start;
+                        let end = ident.span().byte_range().end;
+                        match lifetime {
+                            true => self.register_lifetime_at(start, end, &ident.to_string()),
+                            false => self.register_tag_at_index(start, end, TokenTag::Parameter),
+                        }
+                        iter.next();
+                    }
+                }
+                TokenTree::Ident(ident) => match iter.peek() {
+                    Some(TokenTree::Punct(bang))
+                        if bang.as_char() == '!' && bang.spacing() == Spacing::Alone =>
+                    {
+                        self.register_macro_tag(&ident);
+                        self.register_macro_tag(bang);
+                        iter.next();
+                    }
+                    Some(TokenTree::Group(group))
+                        if group.delimiter() == Delimiter::Parenthesis =>
+                    {
+                        self.register_ident_token(&ident, true);
+                    }
+                    _ => self.register_ident_token(&ident, false),
+                },
+            }
+        }
+    }
+
+    fn register_literal_token(&mut self, token: Literal) {
+        self.register_lit(&Lit::new(token));
+    }
+
+    /// Register an identifier by what is known about its name, as there is no
+    /// syntax to tell what it is.
+    fn register_ident_token(&mut self, token: &Ident, called: bool) {
+        let name = token.to_string();
+        if name == "true" || name == "false" {
+            self.register_litbool_tag(token);
+        } else if KEYWORDS.contains(&name.as_str()) {
+            self.register_lexical_keyword(token);
+        } else if let Some(binding) = self.resolve_binding(&name) {
+            self.register_binding(token, binding);
+        } else if let Some(generic) = self.resolve_generic(&name) {
+            self.register_tag(token, generic);
+        } else if let Some(tag) = self.ident_map.get(name.as_str()).copied() {
+            self.register_tag(token, tag);
+        } else if PRIMITIVE_TYPES.contains(&name.as_str()) {
+            self.register_primitive_tag(token);
+        } else if called {
+            self.register_tag(token, TokenTag::Function);
+        } else {
+            self.register_tag(token, TokenTag::Ident);
+        }
+    }
+}
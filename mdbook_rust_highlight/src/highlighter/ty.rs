@@ -1,8 +1,13 @@
-use syn::{ReturnType, Type, TypeImplTrait, TypePath, TypeReference, TypeTuple};
+use mdbook_rust_highlight_derive::add_try_method;
+use syn::{
+    ReturnType, Type, TypeArray, TypeBareFn, TypeImplTrait, TypePath, TypePtr, TypeReference,
+    TypeTraitObject, TypeTuple,
+};
 
 use crate::{highlighter::RustHighlighter, tokens::TokenTag};
 
 impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    #[add_try_method]
     pub(crate) fn register_type(&mut self, token: &'ast Type) {
         match token {
             Type::Reference(token) => {
@@ -17,13 +22,43 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
             Type::ImplTrait(token) => {
                 self.register_impl_trait_type(token);
             }
+            Type::Infer(token) => {
+                self.register_typeinferred_tag(token);
+            }
+            Type::Array(token) => {
+                self.register_array_type(token);
+            }
+            Type::Slice(token) => {
+                self.register_type(&token.elem);
+            }
+            Type::Ptr(token) => {
+                self.register_ptr_type(token);
+            }
+            Type::BareFn(token) => {
+                self.register_bare_fn_type(token);
+            }
+            Type::TraitObject(token) => {
+                self.register_trait_object_type(token);
+            }
+            Type::Never(token) => {
+                self.register_typenever_tag(token);
+            }
+            Type::Paren(token) => {
+                self.register_type(&token.elem);
+            }
+            Type::Group(token) => {
+                self.register_type(&token.elem);
+            }
+            Type::Macro(token) => {
+                self.register_macro(&token.mac);
+            }
             _ => {}
         }
     }
 
     pub(crate) fn register_reference_type(&mut self, token: &'ast TypeReference) {
-        self.try_register_lifetime_tag(token.lifetime.as_ref());
-        self.try_register_keyword_tag(token.mutability.as_ref());
+        self.try_register_lifetime(token.lifetime.as_ref());
+        self.try_register_keywordstorage_tag(token.mutability.as_ref());
         self.register_type(&token.elem);
     }
 
@@ -32,6 +67,42 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
         self.register_path(&token.path, Some(TokenTag::Type));
     }
 
+    pub(crate) fn register_array_type(&mut self, token: &'ast TypeArray) {
+        self.register_type(&token.elem);
+        self.register_expr(&token.len);
+    }
+
+    pub(crate) fn register_ptr_type(&mut self, token: &'ast TypePtr) {
+        self.register_operator_tag(&token.star_token);
+        self.try_register_keywordstorage_tag(token.const_token.as_ref());
+        self.try_register_keywordstorage_tag(token.mutability.as_ref());
+        self.register_type(&token.elem);
+    }
+
+    pub(crate) fn register_bare_fn_type(&mut self, token: &'ast TypeBareFn) {
+        self.try_register_bound_lifetimes(token.lifetimes.as_ref());
+        self.try_register_keyword_tag(token.unsafety.as_ref());
+        self.try_register_abi(token.abi.as_ref());
+        self.register_keyworddeclaration_tag(&token.fn_token);
+        for input in &token.inputs {
+            if let Some((name, _)) = &input.name {
+                self.register_parameter_tag(name);
+            }
+            self.register_type(&input.ty);
+        }
+        if let Some(variadic) = &token.variadic {
+            self.register_operator_tag(&variadic.dots);
+        }
+        self.register_return_type(&token.output);
+    }
+
+    pub(crate) fn register_trait_object_type(&mut self, token: &'ast TypeTraitObject) {
+        self.try_register_keyword_tag(token.dyn_token.as_ref());
+        for bound in &token.bounds {
+            self.register_bound(bound);
+        }
+    }
+
     pub(crate) fn register_tuple_type(&mut self, token: &'ast TypeTuple) {
         for arg in &token.elems {
             self.register_type(arg);
@@ -39,7 +110,7 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     }
 
     pub(crate) fn register_impl_trait_type(&mut self, token: &'ast TypeImplTrait) {
-        self.register_keyword_tag(&token.impl_token);
+        self.register_keyworddeclaration_tag(&token.impl_token);
         for bound in &token.bounds {
             self.register_bound(bound);
         }
@@ -48,9 +119,23 @@ impl<'a, 'ast> RustHighlighter<'a, 'ast> {
     pub(crate) fn register_return_type(&mut self, token: &'ast ReturnType) {
         match token {
             ReturnType::Default => {}
-            ReturnType::Type(_, token) => {
+            ReturnType::Type(arrow, token) => {
+                self.register_operator_tag(arrow);
                 self.register_type(token);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn builtin_types_have_their_own_tags() {
+        let html = highlight("fn f(p: *const u8) -> ! {\n    let v: Vec<_> = g();\n}\n");
+        assert!(html.contains("<span class=\"hlrs-Operator\">*</span>"));
+        assert!(html.contains("<span class=\"hlrs-Type hlrs-TypeNever\">!</span>"));
+        assert!(html.contains("<span class=\"hlrs-Type hlrs-TypeInferred\">_</span>"));
+    }
+}
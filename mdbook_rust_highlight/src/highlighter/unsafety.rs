@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use syn::{
+    Expr, ForeignItemFn, Ident, ImplItem, ItemFn, ItemImpl, ItemStatic, ItemTrait, ItemUnion, Pat,
+    StaticMutability, TraitItem, Type, visit::Visit,
+};
+
+use crate::{
+    highlighter::{RustHighlighter, scope::BindingType},
+    tokens::Modifier,
+};
+
+/// Items of a snippet whose use requires `unsafe`.
+///
+/// Collected before highlighting so that uses preceding a definition are
+/// recognized as well.
+#[derive(Default)]
+pub(crate) struct UnsafeItems {
+    /// Free and foreign functions, by name.
+    pub(crate) functions: HashSet<String>,
+    /// Associated functions, by `Type::name`, where `Type` is the last
+    /// segment of the implementing type, or the trait declaring them.
+    pub(crate) methods: HashSet<String>,
+    pub(crate) mutable_statics: HashSet<String>,
+    pub(crate) unions: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for UnsafeItems {
+    fn visit_item_fn(&mut self, i: &'ast ItemFn) {
+        if i.sig.unsafety.is_some() {
+            self.functions.insert(i.sig.ident.to_string());
+        }
+        syn::visit::visit_item_fn(self, i);
+    }
+
+    fn visit_item_impl(&mut self, i: &'ast ItemImpl) {
+        if let Some(self_type) = type_name(&i.self_ty) {
+            for item in &i.items {
+                if let ImplItem::Fn(item) = item
+                    && item.sig.unsafety.is_some()
+                {
+                    self.methods
+                        .insert(format!("{self_type}::{}", item.sig.ident));
+                }
+            }
+        }
+        syn::visit::visit_item_impl(self, i);
+    }
+
+    fn visit_item_trait(&mut self, i: &'ast ItemTrait) {
+        for item in &i.items {
+            if let TraitItem::Fn(item) = item
+                && item.sig.unsafety.is_some()
+            {
+                self.methods
+                    .insert(format!("{}::{}", i.ident, item.sig.ident));
+            }
+        }
+        syn::visit::visit_item_trait(self, i);
+    }
+
+    fn visit_foreign_item_fn(&mut self, i: &'ast ForeignItemFn) {
+        self.functions.insert(i.sig.ident.to_string());
+    }
+
+    fn visit_item_static(&mut self, i: &'ast ItemStatic) {
+        if let StaticMutability::Mut(_) = i.mutability {
+            self.mutable_statics.insert(i.ident.to_string());
+        }
+        syn::visit::visit_item_static(self, i);
+    }
+
+    fn visit_item_union(&mut self, i: &'ast ItemUnion) {
+        self.unions.insert(i.ident.to_string());
+    }
+}
+
+/// The name of a type its associated functions are looked up by, the last
+/// segment of its path.
+pub(crate) fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        Type::Paren(ty) => type_name(&ty.elem),
+        _ => None,
+    }
+}
+
+impl<'a, 'ast> RustHighlighter<'a, 'ast> {
+    /// Whether the associated function `name` of the type or trait named
+    /// `owner` requires `unsafe`, where `Self` stands for the type of the
+    /// enclosing impl or trait.
+    pub(crate) fn is_unsafe_method(&self, owner: &Ident, name: &Ident) -> bool {
+        let owner = match (owner == "Self", &self.self_type) {
+            (false, _) => owner.to_string(),
+            (true, Some(self_type)) => self_type.clone(),
+            (true, None) => return false,
+        };
+        self.unsafe_items
+            .methods
+            .contains(&format!("{owner}::{name}"))
+    }
+
+    /// Remember what a simple `name: ty = init` binding holds, so that
+    /// dereferencing it or reading its fields can be marked unsafe, and
+    /// that it holds a reference.
+    pub(crate) fn annotate_binding(&mut self, pat: &Pat, ty: Option<&Type>, init: Option<&Expr>) {
+        let (ident, ty) = match pat {
+            Pat::Ident(pat) => (&pat.ident, ty),
+            Pat::Type(pat) => match &*pat.pat {
+                Pat::Ident(inner) => (&inner.ident, Some(&*pat.ty)),
+                _ => return,
+            },
+            _ => return,
+        };
+        let binding_type = match ty {
+            Some(ty) => self.type_binding_type(ty),
+            None => init.map_or(BindingType::Unknown, |init| self.expr_binding_type(init)),
+        };
+        let reference = match ty {
+            Some(ty) => matches!(ty, Type::Reference(_)),
+            None => matches!(init, Some(Expr::Reference(_))),
+        };
+        if let Some(binding) = self.binding_mut(ident) {
+            binding.ty = binding_type;
+            if reference {
+                binding.modifiers.insert(Modifier::Reference);
+            }
+        }
+        if reference {
+            self.register_modifier(ident, Modifier::Reference);
+        }
+    }
+
+    pub(crate) fn type_binding_type(&self, ty: &Type) -> BindingType {
+        match ty {
+            Type::Ptr(_) => BindingType::RawPointer,
+            Type::Path(ty) => match ty.path.segments.last() {
+                Some(segment)
+                    if self
+                        .unsafe_items
+                        .unions
+                        .contains(&segment.ident.to_string()) =>
+                {
+                    BindingType::Union
+                }
+                _ => BindingType::Unknown,
+            },
+            Type::Paren(ty) => self.type_binding_type(&ty.elem),
+            _ => BindingType::Unknown,
+        }
+    }
+
+    pub(crate) fn expr_binding_type(&self, expr: &Expr) -> BindingType {
+        match expr {
+            Expr::Cast(expr) => self.type_binding_type(&expr.ty),
+            Expr::RawAddr(_) => BindingType::RawPointer,
+            // Methods of raw pointers returning another one.
+            Expr::MethodCall(expr)
+                if matches!(
+                    expr.method.to_string().as_str(),
+                    "cast" | "cast_mut" | "cast_const" | "add" | "sub" | "offset" | "wrapping_add"
+                ) && self.is_raw_pointer(&expr.receiver) =>
+            {
+                BindingType::RawPointer
+            }
+            Expr::Call(expr) => match &*expr.func {
+                Expr::Path(func) => match func.path.segments.last() {
+                    Some(segment) if segment.ident == "null" || segment.ident == "null_mut" => {
+                        BindingType::RawPointer
+                    }
+                    _ => BindingType::Unknown,
+                },
+                _ => BindingType::Unknown,
+            },
+            Expr::Struct(expr) => match expr.path.segments.last() {
+                Some(segment)
+                    if self
+                        .unsafe_items
+                        .unions
+                        .contains(&segment.ident.to_string()) =>
+                {
+                    BindingType::Union
+                }
+                _ => BindingType::Unknown,
+            },
+            Expr::Path(expr) => match expr.path.get_ident() {
+                Some(ident) => self
+                    .resolve_binding(ident)
+                    .map_or(BindingType::Unknown, |binding| binding.ty),
+                None => BindingType::Unknown,
+            },
+            Expr::Paren(expr) => self.expr_binding_type(&expr.expr),
+            _ => BindingType::Unknown,
+        }
+    }
+
+    /// Whether dereferencing the expression dereferences a raw pointer.
+    pub(crate) fn is_raw_pointer(&self, expr: &Expr) -> bool {
+        self.expr_binding_type(expr) == BindingType::RawPointer
+    }
+
+    /// Whether reading a field of the expression reads a union field.
+    pub(crate) fn is_union(&self, expr: &Expr) -> bool {
+        self.expr_binding_type(expr) == BindingType::Union
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::highlighter::highlight;
+
+    #[test]
+    fn pointer_methods_need_a_pointer_receiver() {
+        let html = highlight(
+            "fn f(a: Num, p: *const u8) {\n    let n = a.add(b);\n    *n;\n    let q = p.add(1);\n    *q;\n}\n",
+        );
+        assert_eq!(html.matches("hlrs-mod-unsafe").count(), 1);
+        assert!(
+            html.contains(
+                "hlrs-mod-unsafe\">*</span><span class=\"hlrs-Ident hlrs-Local\">q</span>"
+            )
+        );
+    }
+
+    #[test]
+    fn unsafe_methods_are_matched_by_their_type() {
+        let html = highlight(
+            "struct Raw;\nimpl Raw {\n    unsafe fn new() -> Self { Raw }\n    fn safe(&self) { Self::new(); }\n}\nfn main() {\n    Vec::new();\n    Raw::new();\n    v.new();\n}\n",
+        );
+        assert!(
+            html.contains("Self</span>::<span class=\"hlrs-Function hlrs-mod-unsafe\">new</span>")
+        );
+        assert!(
+            html.contains("Raw</span>::<span class=\"hlrs-Function hlrs-mod-unsafe\">new</span>")
+        );
+        assert!(!html.contains("Vec</span>::<span class=\"hlrs-Function hlrs-mod-unsafe\">"));
+        assert!(!html.contains(".<span class=\"hlrs-Function hlrs-mod-unsafe\">"));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod builtins;
 pub mod highlighter;
 pub mod preprocessor;
 pub mod tokens;
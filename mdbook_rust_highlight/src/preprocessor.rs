@@ -1,6 +1,10 @@
 use std::collections::{BTreeMap, HashMap};
 
-use crate::{highlighter::RustHighlighter, tokens::TokenTag};
+use crate::{
+    builtins::{STD_ENUMS, STD_STRUCTS, STD_TRAITS, STD_VARIANTS},
+    highlighter::RustHighlighter,
+    tokens::TokenTag,
+};
 use mdbook::{
     BookItem,
     book::{Book, Chapter},
@@ -11,30 +15,75 @@ use ropey::Rope;
 
 pub struct RustHighlighterPreprocessor;
 
-const HLRS_CODEBLOCK_REGEX: &str = r"```hlrs(?:,?([^\n]+))?\n([\s\S]*?)\n?```";
+/// The opening fence of a code block, whose closing fence is found by
+/// [`closing_fence`], as the length of a fence can't be matched back.
+const HLRS_FENCE_REGEX: &str = r"(?m)^ {0,3}(`{3,})hlrs(?:,?([^\n]+))?\n";
 const RUST_ICON_URL: &str = "@https://www.rust-lang.org/static/images/rust-logo-blk.svg";
 
 pub type IdentMap<'a> = &'a mut HashMap<&'static str, TokenTag>;
 
+/// The end of the code of a block starting at `start`, and the end of the
+/// fence closing it.
+///
+/// Like in CommonMark, the block is closed by a line of at least `fence`
+/// backticks, so a longer fence can hold doc comments with doctest fences.
+/// An unclosed block runs to the end of the chapter.
+fn closing_fence(content: &str, start: usize, fence: usize) -> (usize, usize) {
+    let mut line_start = start;
+    for line in content[start..].split_inclusive('\n') {
+        let trimmed = line.trim_start_matches(' ');
+        let backticks = trimmed.bytes().take_while(|byte| *byte == b'`').count();
+        if line.len() - trimmed.len() <= 3
+            && backticks >= fence
+            && trimmed[backticks..].trim().is_empty()
+        {
+            // The line break before the fence isn't part of the code.
+            let code_end = line_start.saturating_sub(1).max(start);
+            return (code_end, line_start + line.trim_end_matches('\n').len());
+        }
+        line_start += line.len();
+    }
+    (content.len(), content.len())
+}
+
+/// Options of the `[preprocessor.rust-highlight]` table that change how code
+/// is highlighted.
+#[derive(Debug, Clone)]
+pub struct HighlightConfig {
+    /// Highlight the Markdown inside doc comments, `doc-markdown` in `book.toml`.
+    pub doc_markdown: bool,
+    /// The summary of a region of hidden lines, where `{}` is replaced by
+    /// their number, `hidden-placeholder` in `book.toml`.
+    pub hidden_placeholder: String,
+    /// Show hidden regions until they are collapsed, `show-hidden` in `book.toml`.
+    pub show_hidden: bool,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            doc_markdown: false,
+            hidden_placeholder: String::from("// hidden lines: {}"),
+            show_hidden: false,
+        }
+    }
+}
+
 impl Preprocessor for RustHighlighterPreprocessor {
     fn name(&self) -> &str {
         "rust-highlight"
     }
     fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> mdbook::errors::Result<Book> {
         let ident_map: IdentMap = &mut HashMap::new();
-        // Maybe turn into an initialize function.
-        ident_map.insert("Ok", TokenTag::Enum);
-        ident_map.insert("Err", TokenTag::Enum);
-        ident_map.insert("self", TokenTag::SelfToken);
-        ident_map.insert("Self", TokenTag::SelfToken);
-        // ident_map.insert("Ok", TokenTag::Enum);
+        Self::initialize_ident_map(ident_map);
+        let config = self.highlight_config(ctx);
 
-        // Regex matches entire Rust code blocks including fences
-        let block_pat = Regex::new(HLRS_CODEBLOCK_REGEX).unwrap();
+        // Regex matches the opening fences of Rust code blocks
+        let block_pat = Regex::new(HLRS_FENCE_REGEX).unwrap();
         for item in &mut book.sections {
             if let BookItem::Chapter(chapter) = item {
                 let registered_blocks =
-                    self.register_codeblock(ctx, chapter, &block_pat, ident_map);
+                    self.register_codeblock(ctx, chapter, &block_pat, ident_map, &config);
 
                 Self::write_codeblock(chapter, registered_blocks);
             }
@@ -47,34 +96,52 @@ impl Preprocessor for RustHighlighterPreprocessor {
 }
 
 impl RustHighlighterPreprocessor {
+    fn initialize_ident_map(ident_map: IdentMap) {
+        ident_map.insert("self", TokenTag::SelfToken);
+        ident_map.insert("Self", TokenTag::SelfToken);
+        for (names, tag) in [
+            (STD_TRAITS, TokenTag::Trait),
+            (STD_STRUCTS, TokenTag::Struct),
+            (STD_ENUMS, TokenTag::Enum),
+            (STD_VARIANTS, TokenTag::Variant),
+        ] {
+            for name in names {
+                ident_map.insert(name, tag);
+            }
+        }
+    }
+
     fn register_codeblock(
         &self,
         ctx: &PreprocessorContext,
         chapter: &Chapter,
         pattern: &Regex,
         ident_map: IdentMap,
+        config: &HighlightConfig,
     ) -> BTreeMap<usize, (usize, String)> {
         const GROUP_FULL: usize = 0;
-        const GROUP_FEATURES: usize = 1;
-        const GROUP_CODE: usize = 2;
+        const GROUP_FENCE: usize = 1;
+        const GROUP_FEATURES: usize = 2;
 
         let mut chap_replacement = BTreeMap::new();
 
-        for caps in pattern.captures_iter(&chapter.content) {
+        let mut search_start = 0;
+        let mut block = 0;
+        while let Some(caps) = pattern.captures_at(&chapter.content, search_start) {
             let full = caps.get(GROUP_FULL).unwrap();
-            let code_match = match caps.get(GROUP_CODE) {
-                Some(m) => m,
-                None => continue,
-            };
+            let fence = caps.get(GROUP_FENCE).unwrap().len();
+            let (code_end, end) = closing_fence(&chapter.content, full.end(), fence);
+            search_start = end;
 
             let features = self.whichlang_features(ctx, caps.get(GROUP_FEATURES));
 
-            let code = code_match.as_str();
-            let highlighted = RustHighlighter::highlight(code, ident_map);
+            let code = &chapter.content[full.end()..code_end];
+            let highlighted = RustHighlighter::highlight(code, block, ident_map, config);
+            block += 1;
             let html =
                 format!("<pre><code class=\"language-hlrs {features}\">{highlighted}</code></pre>");
 
-            chap_replacement.insert(full.start(), (full.end(), html));
+            chap_replacement.insert(full.start(), (end, html));
         }
         chap_replacement
     }
@@ -90,6 +157,30 @@ impl RustHighlighterPreprocessor {
         chapter.content = chap_rope.to_string();
     }
 
+    fn highlight_config(&self, ctx: &PreprocessorContext) -> HighlightConfig {
+        let mut config = HighlightConfig::default();
+        let Some(cfg) = ctx.config.get(&format!("preprocessor.{}", self.name())) else {
+            return config;
+        };
+        if let Some(doc_markdown) = cfg.get("doc-markdown") {
+            config.doc_markdown = doc_markdown
+                .as_bool()
+                .expect("\nERROR: `doc-markdown` configuration should be a boolean");
+        }
+        if let Some(placeholder) = cfg.get("hidden-placeholder") {
+            config.hidden_placeholder = placeholder
+                .as_str()
+                .expect("\nERROR: `hidden-placeholder` configuration should be a string")
+                .to_string();
+        }
+        if let Some(show_hidden) = cfg.get("show-hidden") {
+            config.show_hidden = show_hidden
+                .as_bool()
+                .expect("\nERROR: `show-hidden` configuration should be a boolean");
+        }
+        config
+    }
+
     fn whichlang_features<'a>(
         &self,
         ctx: &PreprocessorContext,
@@ -112,6 +203,47 @@ impl RustHighlighterPreprocessor {
             feature_string.push_str(" icon=");
             feature_string.push_str(RUST_ICON_URL);
         }
-        return feature_string;
+        feature_string
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::{HLRS_FENCE_REGEX, closing_fence};
+
+    /// The code of every block of a chapter.
+    fn blocks(content: &str) -> Vec<&str> {
+        let pattern = Regex::new(HLRS_FENCE_REGEX).unwrap();
+        let mut blocks = Vec::new();
+        let mut search_start = 0;
+        while let Some(caps) = pattern.captures_at(content, search_start) {
+            let full = caps.get(0).unwrap();
+            let (code_end, end) = closing_fence(content, full.end(), caps[1].len());
+            blocks.push(&content[full.end()..code_end]);
+            search_start = end;
+        }
+        blocks
+    }
+
+    #[test]
+    fn blocks_close_at_their_own_fence() {
+        assert_eq!(
+            blocks("```hlrs\nfn a() {}\n```\ntext\n```hlrs,fp=main.rs\nfn b() {}\n```\n"),
+            ["fn a() {}", "fn b() {}"]
+        );
+    }
+
+    #[test]
+    fn doctest_fences_stay_inside_the_block() {
+        let content = "```hlrs\n/// ```\n/// f();\n/// ```\nfn f() {}\n```\n";
+        assert_eq!(blocks(content), ["/// ```\n/// f();\n/// ```\nfn f() {}"]);
+    }
+
+    #[test]
+    fn longer_fences_hold_shorter_ones() {
+        let content = "````hlrs\n/**\n```\nf();\n```\n*/\nfn f() {}\n````\n";
+        assert_eq!(blocks(content), ["/**\n```\nf();\n```\n*/\nfn f() {}"]);
     }
 }
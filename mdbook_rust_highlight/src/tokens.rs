@@ -1,60 +1,254 @@
-use std::cmp::{Ordering, Reverse};
+use std::{
+    cmp::{Ordering, Reverse},
+    fmt,
+};
 
 use crate::highlighter::RustHighlighter;
 use mdbook_rust_highlight_derive::{RegisterVariants, add_try_method};
-use strum_macros::AsRefStr;
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
 
 #[derive(AsRefStr, RegisterVariants, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
 pub enum TokenTag {
+    /// A region of hidden lines, first so that it encloses the tokens
+    /// sharing its span.
+    Boring,
     Keyword,
+    KeywordControl,
+    KeywordStorage,
+    KeywordDeclaration,
+    KeywordVisibility,
     Ident,
+    Field,
+    Parameter,
+    Local,
     LitStr,
+    LitRawStr,
+    LitChar,
+    LitByte,
+    LitEscape,
     LitNum,
+    LitSuffix,
     LitBool,
     Function,
     SelfToken,
     Macro,
+    Attribute,
     Type,
+    Primitive,
+    Struct,
     Enum,
+    Union,
+    Trait,
+    TypeAlias,
+    TypeParam,
+    /// `_` in a type, inferred by the compiler.
+    TypeInferred,
+    /// `!`, the type of expressions that never complete.
+    TypeNever,
+    ConstParam,
+    /// The name of a `const` item or associated constant.
+    Constant,
+    /// The name of a `static` item.
+    Static,
+    Variant,
     Segment,
     Comment,
+    DocComment,
+    DocHeading,
+    DocCode,
+    DocLink,
+    DocFence,
     LifeTime,
+    LifeTimeStatic,
+    LifeTimeElided,
+    Label,
+    FormatSpecifier,
+    AsmMnemonic,
+    AsmRegister,
+    AsmOption,
+    Operator,
+    OperatorArithmetic,
+    OperatorBitwise,
+    OperatorComparison,
+    OperatorLogical,
+    OperatorAssignment,
+    OperatorDeref,
+    OperatorBorrow,
+    OperatorTry,
     NeedIdentification,
-    Boring,
-    EndOfToken,
 }
 
-impl ToString for TokenTag {
-    fn to_string(&self) -> String {
+impl TokenTag {
+    /// The broader tag this one refines, if any.
+    ///
+    /// Both classes are emitted, so themes that don't style the refined tag
+    /// fall back to the parent's style.
+    pub(crate) fn parent(&self) -> Option<TokenTag> {
+        match self {
+            Self::OperatorArithmetic
+            | Self::OperatorBitwise
+            | Self::OperatorComparison
+            | Self::OperatorLogical
+            | Self::OperatorAssignment
+            | Self::OperatorDeref
+            | Self::OperatorBorrow
+            | Self::OperatorTry => Some(Self::Operator),
+            Self::Field
+            | Self::Parameter
+            | Self::Local
+            | Self::ConstParam
+            | Self::Constant
+            | Self::Static => Some(Self::Ident),
+            Self::Primitive
+            | Self::Struct
+            | Self::Enum
+            | Self::Union
+            | Self::Trait
+            | Self::TypeAlias
+            | Self::TypeParam
+            | Self::TypeInferred
+            | Self::TypeNever => Some(Self::Type),
+            Self::AsmMnemonic | Self::AsmOption => Some(Self::Keyword),
+            Self::AsmRegister => Some(Self::Ident),
+            Self::DocComment => Some(Self::Comment),
+            Self::KeywordControl
+            | Self::KeywordStorage
+            | Self::KeywordDeclaration
+            | Self::KeywordVisibility => Some(Self::Keyword),
+            Self::LifeTimeStatic | Self::LifeTimeElided => Some(Self::LifeTime),
+            Self::LitRawStr | Self::LitChar | Self::LitByte => Some(Self::LitStr),
+            _ => None,
+        }
+    }
+
+    /// The classes of the tag, its parent's first.
+    pub(crate) fn classes(&self) -> String {
+        let classes = match self.parent() {
+            Some(parent) => format!("hlrs-{} hlrs-{}", parent.as_ref(), self.as_ref()),
+            None => format!("hlrs-{}", self.as_ref()),
+        };
+        match self {
+            // `?` returns early, like the control flow keywords.
+            Self::OperatorTry => format!("{classes} hlrs-{}", Self::KeywordControl.as_ref()),
+            _ => classes,
+        }
+    }
+
+    /// The end of the element the tag opens.
+    pub(crate) fn closing(&self) -> &'static str {
+        match self {
+            Self::Boring => "</details>",
+            _ => "</span>",
+        }
+    }
+
+    /// Whether the tag names a type, in any of its refinements.
+    pub(crate) fn is_type(&self) -> bool {
+        *self == Self::Type || self.parent() == Some(Self::Type)
+    }
+}
+
+impl fmt::Display for TokenTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Boring => String::from("<span class=\"boring\">"),
-            Self::EndOfToken => String::from("</span>"),
-            _ => format!("<span class=\"hlrs-{}\">", self.as_ref()),
+            Self::Boring => write!(f, "<details class=\"boring\">"),
+            _ => write!(f, "<span class=\"{}\">", self.classes()),
         }
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// A property of a token, independent of its tag, modelled on the modifiers
+/// of LSP semantic tokens.
+#[derive(AsRefStr, EnumIter, Debug, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Modifier {
+    /// The name of a binding, parameter or label where it is introduced.
+    Declaration,
+    /// The name of an item where it is defined.
+    Definition,
+    Mutable,
+    Unsafe,
+    Static,
+    Async,
+    Deprecated,
+    /// A name from the standard library.
+    Library,
+    TraitMethod,
+    /// A `self` receiver taken by value.
+    Consuming,
+    /// A binding holding a reference.
+    Reference,
+}
+
+/// A set of modifiers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers(u16);
+
+impl Modifiers {
+    pub(crate) fn insert(&mut self, modifier: Modifier) {
+        self.0 |= 1 << modifier as u16;
+    }
+
+    pub(crate) fn contains(&self, modifier: Modifier) -> bool {
+        self.0 & (1 << modifier as u16) != 0
+    }
+
+    pub(crate) fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Modifier> + '_ {
+        Modifier::iter().filter(|modifier| self.contains(*modifier))
+    }
+}
+
+/// A tag over a range of the highlighted code.
+///
+/// Tokens are ordered by start, then from the longest, then by tag, so that
+/// a token comes before the ones it encloses. Two tokens with the same tag
+/// over the same range are equal whatever their modifiers, as they are a
+/// single token registered twice.
+#[derive(Debug, Clone)]
 pub struct SpannedToken {
     pub(crate) kind: TokenTag,
     pub(crate) start: usize,
     pub(crate) end: usize,
+    pub(crate) modifiers: Modifiers,
+}
+
+impl SpannedToken {
+    /// The classes of the tag followed by one `hlrs-mod-*` class per modifier.
+    pub(crate) fn classes(&self) -> String {
+        let mut classes = self.kind.classes();
+        for modifier in self.modifiers.iter() {
+            classes.push_str(" hlrs-mod-");
+            classes.push_str(modifier.as_ref());
+        }
+        classes
+    }
 }
 
+impl PartialEq for SpannedToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SpannedToken {}
+
 impl PartialOrd for SpannedToken {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        (self.start, Reverse(self.end), self.kind.clone()).partial_cmp(&(
-            other.start,
-            Reverse(other.end),
-            other.kind.clone(),
-        ))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for SpannedToken {
     fn cmp(&self, other: &Self) -> Ordering {
-        (self.start, Reverse(self.end), self.kind.clone())
-            .cmp(&(other.start, Reverse(other.end), other.kind.clone()))
-            .then(Ordering::Greater)
+        (self.start, Reverse(self.end), self.kind).cmp(&(
+            other.start,
+            Reverse(other.end),
+            other.kind,
+        ))
     }
 }